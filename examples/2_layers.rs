@@ -1,4 +1,5 @@
 use smolmatrix::*;
+use smolnn::costs::Cost;
 use smolnn::*;
 
 const SAMPLES_SQRT: isize = 10;
@@ -27,28 +28,23 @@ fn main() {
         let mut r = Vec::new();
         for (_, x) in expected.iter() {
             let r0 = activations::relu(l0.evaluate(x));
-            let rf = activations::softmax(lf.evaluate(&r0));
-            r.push((r0, rf));
+            // the softmax is fused into the cost below, so `lf` outputs raw logits
+            let zf = lf.evaluate(&r0);
+            r.push((r0, zf));
         }
 
         let mut c = 0.0;
         let mut bp0 = BackPropAcc::new();
         let mut bpf = BackPropAcc::new();
-        for ((r0, rf), (e, x)) in r.into_iter().zip(expected.iter()) {
-            c += costs::mse(rf.clone(), e);
-            // NOTE: normally you use this for the cost:
-            // ```
-            // let cost_der = costs::squared_error_derivative(rf.clone(), e);
-            // ```
-            // , however due to impl details, to calculate the cost derivative of a softmax layer
-            // do this instead
-            let cost_der = activations::softmax_cost();
+        for ((r0, zf), (e, x)) in r.into_iter().zip(expected.iter()) {
+            let (ci, cost_der) = costs::SoftmaxCrossEntropy.evaluate(&zf, e);
+            c += ci;
 
             let actv_der_0 = activations::relu_derivative(r0.clone());
-            let actv_der_f = activations::softmax_derivative(rf, e);
+            let actv_der_f = activations::linear_derivative(zf);
 
-            let cost_der = lf.back_prop(&mut bpf, &r0, actv_der_f.clone(), &cost_der, &actv_der_0);
-            l0.back_prop(&mut bp0, x, actv_der_0, &cost_der, &actv_der_f);
+            let cost_der = lf.back_prop(&mut bpf, &r0, actv_der_f, &cost_der, &actv_der_0);
+            l0.back_prop(&mut bp0, x, actv_der_0, &cost_der, &activations::linear_derivative(x.clone()));
         }
 
         l0.apply(bp0, 1.0 / SAMPLES as f32, &mut l0_opt);
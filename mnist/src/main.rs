@@ -19,14 +19,15 @@ fn main() {
     let mut model = model::Model::new();
 
     for i in 1..=100 {
-        let mut epoch = model::Epoch::new();
+        let mut c = 0.0;
 
-        for id in 0..BATCH_SIZE {
+        for _ in 0..BATCH_SIZE {
             let id = alea::u64_in_range(0, images.len() as u64 - 1) as usize;
-            model.feed(&mut epoch, &images[id], labels[id]);
+            c += model.feed(&images[id], labels[id]);
         }
 
-        println!("{i:>5} {}", model.apply(epoch));
+        model.apply();
+        println!("{i:>5} {}", c / BATCH_SIZE as f32);
     }
 
     for (i, l) in images.iter().zip(labels.iter()) {
@@ -1,3 +1,4 @@
+use core::f32::consts::{FRAC_1_SQRT_2, FRAC_2_SQRT_PI};
 use smolmatrix::*;
 
 pub fn linear<const S: usize>(v: Vector<S>) -> Vector<S> {
@@ -16,6 +17,22 @@ pub fn relu_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
     v.map_each(|v| *v = v.signum().max(0.0))
 }
 
+pub fn leaky_relu<const S: usize>(v: Vector<S>, slope: f32) -> Vector<S> {
+    v.map_each(|v| *v = if *v > 0.0 { *v } else { *v * slope })
+}
+
+pub fn leaky_relu_derivative<const S: usize>(v: Vector<S>, slope: f32) -> Vector<S> {
+    v.map_each(|v| *v = if *v > 0.0 { 1.0 } else { slope })
+}
+
+pub fn elu<const S: usize>(v: Vector<S>, alpha: f32) -> Vector<S> {
+    v.map_each(|v| *v = if *v > 0.0 { *v } else { alpha * (v.exp() - 1.0) })
+}
+
+pub fn elu_derivative<const S: usize>(v: Vector<S>, alpha: f32) -> Vector<S> {
+    v.map_each(|v| *v = if *v > 0.0 { 1.0 } else { alpha * v.exp() })
+}
+
 pub fn tanh<const S: usize>(v: Vector<S>) -> Vector<S> {
     v.map_each(|v| *v = v.tanh())
 }
@@ -24,6 +41,108 @@ pub fn tanh_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
     v.map_each(|v| *v = 1.0 - v.tanh().powi(2))
 }
 
+pub fn sigmoid<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = 1.0 / (1.0 + (-*v).exp()))
+}
+
+pub fn sigmoid_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    sigmoid(v).map_each(|s| *s *= 1.0 - *s)
+}
+
+// never exponentiates a positive number, so it neither overflows nor loses precision to `1 + e^x`
+// for large |x|
+pub fn stable_sigmoid<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| {
+        *v = if *v >= 0.0 {
+            1.0 / (1.0 + (-*v).exp())
+        } else {
+            let e = v.exp();
+            e / (1.0 + e)
+        }
+    })
+}
+
+pub fn stable_sigmoid_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    stable_sigmoid(v).map_each(|s| *s *= 1.0 - *s)
+}
+
+// piecewise linear, x / 6 + 1/2 clamped to [0, 1]
+pub fn hard_sigmoid<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = (*v / 6.0 + 0.5).clamp(0.0, 1.0))
+}
+
+pub fn hard_sigmoid_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = if v.abs() < 3.0 { 1.0 / 6.0 } else { 0.0 })
+}
+
+// ln(1 + eˣ), stays exact for large |x| instead of overflowing
+pub fn softplus<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = softplus_f32(*v))
+}
+
+pub fn softplus_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    stable_sigmoid(v)
+}
+
+pub fn softsign<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v /= 1.0 + v.abs())
+}
+
+pub fn softsign_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = 1.0 / (1.0 + v.abs()).powi(2))
+}
+
+// LIGHT:
+// gelu(x) = x Φ(x) = x/2 (1 + erf(x/√2))
+pub fn gelu<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = 0.5 * *v * (1.0 + erf(*v * FRAC_1_SQRT_2)))
+}
+
+// LIGHT:
+// ∂gelu/∂x = Φ(x) + x φ(x)
+pub fn gelu_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = 0.5 * (1.0 + erf(*v * FRAC_1_SQRT_2)) + *v * (-0.5 * *v * *v).exp() * FRAC_1_SQRT_2PI)
+}
+
+// LIGHT:
+// gelu(x) ≈ x/2 (1 + tanh(√(2/π) (x + 0.044715 x³)))
+pub fn gelu_tanh<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v = 0.5 * *v * (1.0 + (SQRT_2_FRAC_PI * (*v + 0.044715 * v.powi(3))).tanh()))
+}
+
+pub fn gelu_tanh_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| {
+        let t = (SQRT_2_FRAC_PI * (*v + 0.044715 * v.powi(3))).tanh();
+        *v = 0.5 * (1.0 + t) + 0.5 * *v * (1.0 - t * t) * SQRT_2_FRAC_PI * (1.0 + 3.0 * 0.044715 * *v * *v)
+    })
+}
+
+// also known as swish
+pub fn silu<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.clone() * &stable_sigmoid(v)
+}
+
+// LIGHT:
+// ∂(x σ(x))/∂x = σ(x) (1 + x (1 - σ(x)))
+pub fn silu_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    let s = stable_sigmoid(v.clone());
+    v.map_zip_ref(&s, |(v, s)| *v = s * (1.0 + *v * (1.0 - s)))
+}
+
+pub fn mish<const S: usize>(v: Vector<S>) -> Vector<S> {
+    v.map_each(|v| *v *= softplus_f32(*v).tanh())
+}
+
+// LIGHT:
+// ∂(x tanh(sp(x)))/∂x = tanh(sp(x)) + x (1 - tanh²(sp(x))) σ(x)
+pub fn mish_derivative<const S: usize>(v: Vector<S>) -> Vector<S> {
+    let s = stable_sigmoid(v.clone());
+    v.map_zip_ref(&s, |(v, s)| {
+        let t = softplus_f32(*v).tanh();
+        *v = t + *v * (1.0 - t * t) * s
+    })
+}
+
 pub fn stable_softmax<const S: usize>(mut v: Vector<S>) -> Vector<S> {
     let max = v.inner.iter().flatten().max_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal)).unwrap();
     v -= *max;
@@ -34,6 +153,14 @@ pub fn stable_softmax_derivative<const S: usize>(v: Vector<S>, t: &Vector<S>) ->
     stable_softmax(v) - t
 }
 
+// LIGHT:
+// log softmax(x)_i = x_i - m - ln Σ e^(x_j - m), m = max x
+pub fn log_softmax<const S: usize>(v: Vector<S>) -> Vector<S> {
+    let max = *v.inner.iter().flatten().max_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal)).unwrap();
+    let lse = max + v.inner.iter().flatten().map(|v| (v - max).exp()).sum::<f32>().ln();
+    v.map_each(|v| *v -= lse)
+}
+
 pub fn softmax<const S: usize>(mut v: Vector<S>) -> Vector<S> {
     v.map_each_in_place(|i| *i = i.exp());
     v /= v.inner.iter().flatten().sum::<f32>();
@@ -44,7 +171,379 @@ pub fn softmax_derivative<const S: usize>(v: Vector<S>, t: &Vector<S>) -> Vector
     softmax(v) - t
 }
 
-pub fn softmax_cost<const S: usize>(v: Vector<S>, t: &mut Vector<S>) -> Vector<S> {
-    t.map_each_in_place(|i| *i = (*i + 1e-15).ln());
-    -(v * &*t)
+pub trait Activation<const S: usize> {
+    fn forward(&self, v: Vector<S>) -> Vector<S>;
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S>;
+
+    // some activations can get their derivative from the output alone, which saves the caller
+    // from keeping the pre-activation values around
+    fn derivative_from_output(&self, _a: &Vector<S>) -> Option<Vector<S>> {
+        None
+    }
+
+    // vector-jacobian product, only activations that mix elements (e.g. softmax) need to
+    // override this
+    fn backward(&self, v: Vector<S>, g: &Vector<S>) -> Vector<S> {
+        self.derivative(v) * g
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Linear;
+
+impl<const S: usize> Activation<S> for Linear {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        linear(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        linear_derivative(v)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(linear_derivative(a.clone()))
+    }
+
+    fn backward(&self, _v: Vector<S>, g: &Vector<S>) -> Vector<S> {
+        g.clone()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Relu;
+
+impl<const S: usize> Activation<S> for Relu {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        relu(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        relu_derivative(v)
+    }
+
+    // dead units output exactly 0, which `relu_derivative` would treat as positive
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|a| *a = if *a > 0.0 { 1.0 } else { 0.0 }))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LeakyRelu {
+    pub slope: f32,
+}
+
+impl Default for LeakyRelu {
+    fn default() -> Self {
+        Self { slope: 0.01 }
+    }
+}
+
+impl<const S: usize> Activation<S> for LeakyRelu {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        leaky_relu(v, self.slope)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        leaky_relu_derivative(v, self.slope)
+    }
+
+    // only holds for positive slopes, which keep the sign of the input
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        (self.slope > 0.0).then(|| leaky_relu_derivative(a.clone(), self.slope))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Elu {
+    pub alpha: f32,
+}
+
+impl Default for Elu {
+    fn default() -> Self {
+        Self { alpha: 1.0 }
+    }
+}
+
+impl<const S: usize> Activation<S> for Elu {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        elu(v, self.alpha)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        elu_derivative(v, self.alpha)
+    }
+
+    // LIGHT:
+    // x ≤ 0 ⇒ ∂y/∂x = α eˣ = y + α
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        (self.alpha > 0.0).then(|| a.clone().map_each(|a| *a = if *a > 0.0 { 1.0 } else { *a + self.alpha }))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tanh;
+
+impl<const S: usize> Activation<S> for Tanh {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        tanh(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        tanh_derivative(v)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|v| *v = 1.0 - *v * *v))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogSoftmax;
+
+impl<const S: usize> Activation<S> for LogSoftmax {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        log_softmax(v)
+    }
+
+    // diagonal of the jacobian only, use `backward` to propagate gradients
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        stable_softmax(v).map_each(|s| *s = 1.0 - *s)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|a| *a = 1.0 - a.exp()))
+    }
+
+    fn backward(&self, v: Vector<S>, g: &Vector<S>) -> Vector<S> {
+        // LIGHT:
+        // ∂y_i/∂z_j = δ_ij - s_j
+        // ⇒ (Jᵀg)_j = g_j - s_j Σg
+        let sum = g.inner.iter().flatten().sum::<f32>();
+        g.clone() - &(stable_softmax(v) * sum)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sigmoid;
+
+impl<const S: usize> Activation<S> for Sigmoid {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        stable_sigmoid(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        stable_sigmoid_derivative(v)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|s| *s *= 1.0 - *s))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HardSigmoid;
+
+impl<const S: usize> Activation<S> for HardSigmoid {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        hard_sigmoid(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        hard_sigmoid_derivative(v)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|a| *a = if *a > 0.0 && *a < 1.0 { 1.0 / 6.0 } else { 0.0 }))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Softplus;
+
+impl<const S: usize> Activation<S> for Softplus {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        softplus(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        softplus_derivative(v)
+    }
+
+    // LIGHT:
+    // σ(x) = 1 - e^(-softplus(x))
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|a| *a = -(-*a).exp_m1()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Softsign;
+
+impl<const S: usize> Activation<S> for Softsign {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        softsign(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        softsign_derivative(v)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|a| *a = (1.0 - a.abs()).powi(2)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gelu;
+
+impl<const S: usize> Activation<S> for Gelu {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        gelu(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        gelu_derivative(v)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GeluTanh;
+
+impl<const S: usize> Activation<S> for GeluTanh {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        gelu_tanh(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        gelu_tanh_derivative(v)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Silu;
+
+impl<const S: usize> Activation<S> for Silu {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        silu(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        silu_derivative(v)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mish;
+
+impl<const S: usize> Activation<S> for Mish {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        mish(v)
+    }
+
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        mish_derivative(v)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Softmax;
+
+impl<const S: usize> Activation<S> for Softmax {
+    fn forward(&self, v: Vector<S>) -> Vector<S> {
+        stable_softmax(v)
+    }
+
+    // diagonal of the jacobian only, use `backward` to propagate gradients
+    fn derivative(&self, v: Vector<S>) -> Vector<S> {
+        stable_softmax(v).map_each(|s| *s *= 1.0 - *s)
+    }
+
+    fn derivative_from_output(&self, a: &Vector<S>) -> Option<Vector<S>> {
+        Some(a.clone().map_each(|s| *s *= 1.0 - *s))
+    }
+
+    fn backward(&self, v: Vector<S>, g: &Vector<S>) -> Vector<S> {
+        // LIGHT:
+        // ∂s_i/∂z_j = s_i (δ_ij - s_j)
+        // ⇒ (Jᵀg)_i = s_i (g_i - s·g)
+        let s = stable_softmax(v);
+        let sg = s.dot(g);
+        g.clone().map_each(|g| *g -= sg) * &s
+    }
+}
+
+const FRAC_1_SQRT_2PI: f32 = FRAC_2_SQRT_PI * FRAC_1_SQRT_2 * 0.5;
+const SQRT_2_FRAC_PI: f32 = FRAC_2_SQRT_PI * FRAC_1_SQRT_2;
+
+// Abramowitz and Stegun 7.1.26, the absolute error stays below 1.5e-7
+fn erf(x: f32) -> f32 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let p = t * (0.2548296 + t * (-0.2844967 + t * (1.4214138 + t * (-1.4531521 + t * 1.0614054))));
+
+    (1.0 - p * (-x * x).exp()).copysign(x)
+}
+
+// LIGHT:
+// ln(1 + eˣ) = max(x, 0) + ln(1 + e^(-|x|))
+fn softplus_f32(x: f32) -> f32 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    fn assert_derivative_from_output<A: Activation<7>>(f: A) {
+        let z = vector!(7 [-5.0, -2.0, -0.1, 0.5, 1.1, 2.5, 4.0]);
+
+        let a = f.derivative_from_output(&f.forward(z.clone())).unwrap();
+        let d = f.derivative(z);
+
+        for i in 0..7 {
+            assert!((a[i] - d[i]).abs() < 1e-5, "{i}: {} != {}", a[i], d[i]);
+        }
+    }
+
+    fn assert_backward<A: Activation<7>>(f: A) {
+        let z = vector!(7 [-5.0, -2.0, -0.1, 0.5, 1.1, 2.5, 4.0]);
+        let g = fill(Vector::<7>::new_zeroed(), 1);
+
+        assert_gradient(&z, &f.backward(z.clone(), &g), |z| dot(&g, &f.forward(z.clone())));
+    }
+
+    #[test]
+    fn derivative_from_output_matches_derivative() {
+        assert_derivative_from_output(Linear);
+        assert_derivative_from_output(Relu);
+        assert_derivative_from_output(Tanh);
+        assert_derivative_from_output(Softmax);
+        assert_derivative_from_output(Sigmoid);
+        assert_derivative_from_output(HardSigmoid);
+        assert_derivative_from_output(LeakyRelu::default());
+        assert_derivative_from_output(Elu::default());
+        assert_derivative_from_output(Softplus);
+        assert_derivative_from_output(Softsign);
+        assert_derivative_from_output(LogSoftmax);
+    }
+
+    #[test]
+    fn backward_matches_forward() {
+        assert_backward(Linear);
+        assert_backward(Relu);
+        assert_backward(Tanh);
+        assert_backward(Softmax);
+        assert_backward(Sigmoid);
+        assert_backward(HardSigmoid);
+        assert_backward(LeakyRelu::default());
+        assert_backward(Elu::default());
+        assert_backward(Gelu);
+        assert_backward(GeluTanh);
+        assert_backward(Silu);
+        assert_backward(Mish);
+        assert_backward(Softplus);
+        assert_backward(Softsign);
+        assert_backward(LogSoftmax);
+    }
 }
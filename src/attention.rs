@@ -0,0 +1,182 @@
+use crate::{activations::*, modules::Module, optimizers::Optimizer, BackPropAcc, Layer};
+use smolmatrix::*;
+
+// multi-head scaled dot-product self-attention over `L` tokens of `D` features, one token per
+// column. `D` must be divisible by `HEADS`.
+//
+// LIGHT:
+// Q = W_q X   K = W_k X   V = W_v X
+// A_h = softmax(Q_h K_hᵀ / √(D/HEADS))
+// Y = W_o [A_1 V_1, ..., A_HEADS V_HEADS]
+//
+// `opt` is in the order query, key, value, output
+#[derive(Debug)]
+pub struct SelfAttention<const L: usize, const D: usize, const HEADS: usize, Opt> {
+    pub query: Layer<D, D>,
+    pub key: Layer<D, D>,
+    pub value: Layer<D, D>,
+    pub output: Layer<D, D>,
+    // stops tokens from attending to later ones
+    pub causal: bool,
+    pub opt: [Opt; 4],
+    acc: [BackPropAcc<D, D>; 4],
+    x: Matrix<L, D>,
+    q: Matrix<L, D>,
+    k: Matrix<L, D>,
+    v: Matrix<L, D>,
+    o: Matrix<L, D>,
+    // `attention[h][(j, i)]` is how much token `i` attends to token `j`
+    attention: [Matrix<L, L>; HEADS],
+}
+
+impl<const L: usize, const D: usize, const HEADS: usize, Opt: Optimizer<D, D>> SelfAttention<L, D, HEADS, Opt> {
+    pub const fn new(layers: [Layer<D, D>; 4], opt: [Opt; 4]) -> Self {
+        let [query, key, value, output] = layers;
+
+        Self {
+            query,
+            key,
+            value,
+            output,
+            causal: false,
+            opt,
+            acc: [const { BackPropAcc::new() }; 4],
+            x: Matrix::new_zeroed(),
+            q: Matrix::new_zeroed(),
+            k: Matrix::new_zeroed(),
+            v: Matrix::new_zeroed(),
+            o: Matrix::new_zeroed(),
+            attention: [const { Matrix::new_zeroed() }; HEADS],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized(opt: [Opt; 4]) -> Self {
+        Self::new(core::array::from_fn(|_| Layer::new_randomized()), opt)
+    }
+}
+
+impl<const L: usize, const D: usize, const HEADS: usize, Opt: Optimizer<D, D>> Module<Matrix<L, D>>
+    for SelfAttention<L, D, HEADS, Opt>
+{
+    type Output = Matrix<L, D>;
+
+    fn forward(&mut self, x: &Matrix<L, D>) -> Matrix<L, D> {
+        const { assert!(D.is_multiple_of(HEADS)) };
+
+        let dh = D / HEADS;
+        let scale = 1.0 / (dh as f32).sqrt();
+
+        self.x = x.clone();
+        self.q = self.query.evaluate_batch(x);
+        self.k = self.key.evaluate_batch(x);
+        self.v = self.value.evaluate_batch(x);
+
+        for (h, a) in self.attention.iter_mut().enumerate() {
+            let dims = h * dh..(h + 1) * dh;
+
+            for i in 0..L {
+                let mut s = Vector::<L>::new_zeroed();
+
+                for j in 0..L {
+                    s[j] = if self.causal && j > i {
+                        f32::NEG_INFINITY
+                    } else {
+                        dims.clone().map(|d| self.q[(i, d)] * self.k[(j, d)]).sum::<f32>() * scale
+                    };
+                }
+
+                let s = Softmax.forward(s);
+
+                for j in 0..L {
+                    a[(j, i)] = s[j];
+                }
+
+                for d in dims.clone() {
+                    self.o[(i, d)] = (0..L).map(|j| s[j] * self.v[(j, d)]).sum();
+                }
+            }
+        }
+
+        self.output.evaluate_batch(&self.o)
+    }
+
+    fn backward(&mut self, g: &Matrix<L, D>) -> Matrix<L, D> {
+        let dh = D / HEADS;
+        let scale = 1.0 / (dh as f32).sqrt();
+
+        let [acc_q, acc_k, acc_v, acc_o] = &mut self.acc;
+        let d_o = self.output.back_prop_batch(acc_o, &self.o, g);
+
+        let mut dq = Matrix::<L, D>::new_zeroed();
+        let mut dk = Matrix::<L, D>::new_zeroed();
+        let mut dv = Matrix::<L, D>::new_zeroed();
+
+        for (h, a) in self.attention.iter().enumerate() {
+            let dims = h * dh..(h + 1) * dh;
+
+            for i in 0..L {
+                let mut da = Vector::<L>::new_zeroed();
+
+                for j in 0..L {
+                    da[j] = dims.clone().map(|d| d_o[(i, d)] * self.v[(j, d)]).sum();
+
+                    for d in dims.clone() {
+                        dv[(j, d)] += a[(j, i)] * d_o[(i, d)];
+                    }
+                }
+
+                // LIGHT:
+                // ∂s_j = a_j (∂a_j - Σ a ∂a)
+                let a_da = (0..L).map(|j| a[(j, i)] * da[j]).sum::<f32>();
+
+                for j in 0..L {
+                    let ds = a[(j, i)] * (da[j] - a_da) * scale;
+
+                    for d in dims.clone() {
+                        dq[(i, d)] += ds * self.k[(j, d)];
+                        dk[(j, d)] += ds * self.q[(i, d)];
+                    }
+                }
+            }
+        }
+
+        let mut dx = self.query.back_prop_batch(acc_q, &self.x, &dq);
+        dx += &self.key.back_prop_batch(acc_k, &self.x, &dk);
+        dx += &self.value.back_prop_batch(acc_v, &self.x, &dv);
+        dx
+    }
+
+    fn update(&mut self, sf: f32) {
+        let layers = [&mut self.query, &mut self.key, &mut self.value, &mut self.output];
+
+        for ((layer, acc), opt) in layers.into_iter().zip(self.acc.iter_mut()).zip(self.opt.iter_mut()) {
+            layer.apply_in_place(acc, sf, opt);
+            acc.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimizers::sgd, testing::*};
+
+    #[test]
+    fn gradients_match_central_differences() {
+        for causal in [false, true] {
+            let new = || {
+                let layers = fill(core::array::from_fn(|_| Layer::new_zeroed()), 1);
+                let mut m = SelfAttention::<3, 4, 2, _>::new(layers, core::array::from_fn(|_| sgd(1.0)));
+                m.causal = causal;
+                m
+            };
+
+            assert_input_gradient(new, Matrix::new_zeroed());
+            assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.query);
+            assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.key);
+            assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.value);
+            assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.output);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{activations::Activation, costs::Cost, BackPropAcc, Layer};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{cell::RefCell, ops::*};
+use smolmatrix::*;
+
+// takes the input value and the output gradient, returns the input gradient
+type MapBackward = Box<dyn Fn(&[f32], &[f32]) -> Vec<f32>>;
+
+enum Op {
+    Leaf,
+    MatMul(usize, usize),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Hadamard(usize, usize),
+    Scale(usize, f32),
+    Transpose(usize),
+    Sum(usize),
+    Map(usize, MapBackward),
+    Cost(usize, Vec<f32>),
+}
+
+struct Node {
+    // row major, same layout as `Matrix::inner`
+    value: Vec<f32>,
+    w: usize,
+    op: Op,
+}
+
+#[derive(Default)]
+pub struct Tape {
+    nodes: RefCell<Vec<Node>>,
+}
+
+impl Tape {
+    pub const fn new() -> Self {
+        Self {
+            nodes: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn var<const W: usize, const H: usize>(&self, m: &Matrix<W, H>) -> Var<'_, W, H> {
+        Var {
+            tape: self,
+            id: self.push(flatten(m), W, Op::Leaf),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.get_mut().clear();
+    }
+
+    fn push(&self, value: Vec<f32>, w: usize, op: Op) -> usize {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(Node { value, w, op });
+        nodes.len() - 1
+    }
+
+    // seeds every element of `v` with a gradient of 1, so `v` is usually a scalar cost
+    pub fn backward<const W: usize, const H: usize>(&self, v: Var<'_, W, H>) -> Gradients {
+        let nodes = self.nodes.borrow();
+        let mut grads = nodes.iter().map(|n| vec![0.0; n.value.len()]).collect::<Vec<_>>();
+        grads[v.id].fill(1.0);
+
+        for id in (0..=v.id).rev() {
+            let g = core::mem::take(&mut grads[id]);
+
+            match &nodes[id].op {
+                Op::Leaf => {},
+                Op::MatMul(a, b) => {
+                    // LIGHT:
+                    // C = AB ⇒ ∂A = ∂C Bᵀ, ∂B = Aᵀ ∂C
+                    let wc = nodes[id].w;
+                    let k = nodes[*a].w;
+                    let (va, vb) = (&nodes[*a].value, &nodes[*b].value);
+
+                    for (y, gy) in g.chunks(wc).enumerate() {
+                        for (x, gyx) in gy.iter().enumerate() {
+                            for i in 0..k {
+                                grads[*a][y * k + i] += gyx * vb[i * wc + x];
+                                grads[*b][i * wc + x] += va[y * k + i] * gyx;
+                            }
+                        }
+                    }
+                },
+                Op::Add(a, b) => {
+                    add_to(&mut grads[*a], &g, 1.0);
+                    add_to(&mut grads[*b], &g, 1.0);
+                },
+                Op::Sub(a, b) => {
+                    add_to(&mut grads[*a], &g, 1.0);
+                    add_to(&mut grads[*b], &g, -1.0);
+                },
+                Op::Hadamard(a, b) => {
+                    for (i, g) in g.iter().enumerate() {
+                        grads[*a][i] += g * nodes[*b].value[i];
+                        grads[*b][i] += g * nodes[*a].value[i];
+                    }
+                },
+                Op::Scale(a, s) => add_to(&mut grads[*a], &g, *s),
+                Op::Transpose(a) => {
+                    let wa = nodes[*a].w;
+
+                    for (x, gx) in g.chunks(nodes[id].w).enumerate() {
+                        for (y, g) in gx.iter().enumerate() {
+                            grads[*a][y * wa + x] += g;
+                        }
+                    }
+                },
+                Op::Sum(a) => grads[*a].iter_mut().for_each(|i| *i += g[0]),
+                Op::Map(a, f) => add_to(&mut grads[*a], &f(&nodes[*a].value, &g), 1.0),
+                Op::Cost(a, d) => add_to(&mut grads[*a], d, g[0]),
+            }
+
+            grads[id] = g;
+        }
+
+        Gradients { inner: grads }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Var<'t, const W: usize, const H: usize> {
+    tape: &'t Tape,
+    id: usize,
+}
+
+impl<'t, const W: usize, const H: usize> Var<'t, W, H> {
+    pub fn value(&self) -> Matrix<W, H> {
+        unflatten(&self.tape.nodes.borrow()[self.id].value)
+    }
+
+    fn unary<const W2: usize, const H2: usize>(self, value: Matrix<W2, H2>, op: Op) -> Var<'t, W2, H2> {
+        Var {
+            tape: self.tape,
+            id: self.tape.push(flatten(&value), W2, op),
+        }
+    }
+
+    pub fn matmul<const WB: usize>(self, b: Var<'t, WB, W>) -> Var<'t, WB, H> {
+        self.unary(&self.value() * &b.value(), Op::MatMul(self.id, b.id))
+    }
+
+    pub fn hadamard(self, b: Self) -> Self {
+        let v = self.value().map_zip_ref(&b.value(), |(i, j)| *i *= j);
+        self.unary(v, Op::Hadamard(self.id, b.id))
+    }
+
+    pub fn transpose(self) -> Var<'t, H, W> {
+        self.unary(self.value().transpose(), Op::Transpose(self.id))
+    }
+
+    pub fn sum(self) -> Var<'t, 1, 1> {
+        let s = self.tape.nodes.borrow()[self.id].value.iter().sum::<f32>();
+        self.unary(vector!(1 [s]), Op::Sum(self.id))
+    }
+}
+
+impl<'t, const H: usize> Var<'t, 1, H> {
+    pub fn activate<A: Activation<H> + 'static>(self, f: A) -> Self {
+        let v = f.forward(self.value());
+        let back = move |z: &[f32], g: &[f32]| flatten(&f.backward(unflatten(z), &unflatten(g)));
+        self.unary(v, Op::Map(self.id, Box::new(back)))
+    }
+
+    pub fn cost<C: Cost<H>>(self, c: &C, t: &Vector<H>) -> Var<'t, 1, 1> {
+        let (c, d) = c.evaluate(&self.value(), t);
+        self.unary(vector!(1 [c]), Op::Cost(self.id, flatten(&d)))
+    }
+}
+
+impl<'t, const W: usize, const H: usize> Add for Var<'t, W, H> {
+    type Output = Self;
+
+    fn add(self, b: Self) -> Self {
+        self.unary(self.value() + &b.value(), Op::Add(self.id, b.id))
+    }
+}
+
+impl<'t, const W: usize, const H: usize> Sub for Var<'t, W, H> {
+    type Output = Self;
+
+    fn sub(self, b: Self) -> Self {
+        self.unary(self.value() - &b.value(), Op::Sub(self.id, b.id))
+    }
+}
+
+impl<'t, const W: usize, const H: usize> Mul<f32> for Var<'t, W, H> {
+    type Output = Self;
+
+    fn mul(self, s: f32) -> Self {
+        self.unary(self.value() * s, Op::Scale(self.id, s))
+    }
+}
+
+pub struct Gradients {
+    inner: Vec<Vec<f32>>,
+}
+
+impl Gradients {
+    pub fn get<const W: usize, const H: usize>(&self, v: Var<'_, W, H>) -> Matrix<W, H> {
+        unflatten(&self.inner[v.id])
+    }
+
+    pub fn accumulate<const IN: usize, const OUT: usize>(
+        &self,
+        vars: &LayerVars<'_, IN, OUT>,
+        acc: &mut BackPropAcc<IN, OUT>,
+    ) {
+        acc.0.weights += &self.get(vars.weights);
+        acc.0.biases += &self.get(vars.biases);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LayerVars<'t, const IN: usize, const OUT: usize> {
+    pub weights: Var<'t, IN, OUT>,
+    pub biases: Var<'t, 1, OUT>,
+}
+
+impl<'t, const IN: usize, const OUT: usize> LayerVars<'t, IN, OUT> {
+    pub fn evaluate(&self, i: Var<'t, 1, IN>) -> Var<'t, 1, OUT> {
+        self.weights.matmul(i) + self.biases
+    }
+}
+
+impl<const IN: usize, const OUT: usize> Layer<IN, OUT> {
+    pub fn vars<'t>(&self, tape: &'t Tape) -> LayerVars<'t, IN, OUT> {
+        LayerVars {
+            weights: tape.var(&self.weights),
+            biases: tape.var(&self.biases),
+        }
+    }
+}
+
+fn add_to(a: &mut [f32], b: &[f32], s: f32) {
+    a.iter_mut().zip(b).for_each(|(a, b)| *a += b * s);
+}
+
+fn flatten<const W: usize, const H: usize>(m: &Matrix<W, H>) -> Vec<f32> {
+    m.inner.iter().flatten().copied().collect()
+}
+
+fn unflatten<const W: usize, const H: usize>(v: &[f32]) -> Matrix<W, H> {
+    let mut m = Matrix::new_zeroed();
+    m.inner.iter_mut().flatten().zip(v).for_each(|(m, v)| *m = *v);
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{activations::Tanh, costs::Mse, testing::*};
+
+    // goes through every op once, returns the cost and the leaves for `a` and `x`
+    fn cost<'t>(tape: &'t Tape, a: &Matrix<3, 2>, x: &Vector<3>) -> (Var<'t, 1, 1>, Var<'t, 3, 2>, Var<'t, 1, 3>) {
+        let (a, x) = (tape.var(a), tape.var(x));
+        let h = a.matmul(x);
+        let h = (h.hadamard(h) - h * 0.5 + h).activate(Tanh);
+
+        (h.transpose().matmul(h).sum() + h.cost(&Mse, &vector!(2 [0.3, -0.7])), a, x)
+    }
+
+    #[test]
+    fn gradients_match_central_differences() {
+        let (a, x) = (fill(Matrix::new_zeroed(), 1), fill(Vector::new_zeroed(), 2));
+
+        let tape = Tape::new();
+        let (c, va, vx) = cost(&tape, &a, &x);
+        let g = tape.backward(c);
+
+        assert_gradient(&a, &g.get(va), |a| cost(&Tape::new(), a, &x).0.value()[0]);
+        assert_gradient(&x, &g.get(vx), |x| cost(&Tape::new(), &a, x).0.value()[0]);
+    }
+}
@@ -0,0 +1,653 @@
+use crate::{modules::{Chain, Module}, optimizers::Optimizer, BackPropAcc, Layer};
+use smolmatrix::*;
+
+// feature maps are `[Matrix<W, H>; C]`
+//
+// `N` must be `CI * K * K` and `OW`/`OH` must be `(W + 2 PAD - K) / STRIDE + 1`, both are
+// checked at compile time.
+//
+// the kernel is a dense layer over flattened patches, row `co` column `ci K² + ky K + kx`
+#[derive(Debug)]
+pub struct Conv2d<
+    const CI: usize,
+    const CO: usize,
+    const K: usize,
+    const STRIDE: usize,
+    const PAD: usize,
+    const W: usize,
+    const H: usize,
+    const N: usize,
+    const OW: usize,
+    const OH: usize,
+    Opt,
+> {
+    pub kernel: Layer<N, CO>,
+    pub opt: Opt,
+    acc: BackPropAcc<N, CO>,
+    // one patch per column for every output row
+    patches: [Matrix<OW, N>; OH],
+}
+
+impl<
+        const CI: usize,
+        const CO: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const W: usize,
+        const H: usize,
+        const N: usize,
+        const OW: usize,
+        const OH: usize,
+        Opt: Optimizer<N, CO>,
+    > Conv2d<CI, CO, K, STRIDE, PAD, W, H, N, OW, OH, Opt>
+{
+    pub const fn new(kernel: Layer<N, CO>, opt: Opt) -> Self {
+        Self {
+            kernel,
+            opt,
+            acc: BackPropAcc::new(),
+            patches: [const { Matrix::new_zeroed() }; OH],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized(opt: Opt) -> Self {
+        Self::new(Layer::new_randomized(), opt)
+    }
+}
+
+impl<
+        const CI: usize,
+        const CO: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const W: usize,
+        const H: usize,
+        const N: usize,
+        const OW: usize,
+        const OH: usize,
+        Opt: Optimizer<N, CO>,
+    > Module<[Matrix<W, H>; CI]> for Conv2d<CI, CO, K, STRIDE, PAD, W, H, N, OW, OH, Opt>
+{
+    type Output = [Matrix<OW, OH>; CO];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; CI]) -> [Matrix<OW, OH>; CO] {
+        const {
+            assert!(N == CI * K * K);
+            assert!(OW == (W + 2 * PAD - K) / STRIDE + 1);
+            assert!(OH == (H + 2 * PAD - K) / STRIDE + 1);
+        }
+
+        let mut o = core::array::from_fn(|_| Matrix::new_zeroed());
+
+        for (oy, p) in self.patches.iter_mut().enumerate() {
+            for ox in 0..OW {
+                for c in 0..CI {
+                    for ky in 0..K {
+                        for kx in 0..K {
+                            let v = source::<STRIDE, PAD, W>(ox, kx)
+                                .zip(source::<STRIDE, PAD, H>(oy, ky))
+                                .map_or(0.0, |xy| i[c][xy]);
+
+                            p[(ox, c * K * K + ky * K + kx)] = v;
+                        }
+                    }
+                }
+            }
+
+            let r = self.kernel.evaluate_batch(p);
+            for (o, r) in o.iter_mut().zip(r.inner.iter()) {
+                o.inner[oy] = *r;
+            }
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &[Matrix<OW, OH>; CO]) -> [Matrix<W, H>; CI] {
+        let mut d = core::array::from_fn(|_| Matrix::<W, H>::new_zeroed());
+
+        for (oy, p) in self.patches.iter().enumerate() {
+            let mut gr = Matrix::<OW, CO>::new_zeroed();
+            for (gr, g) in gr.inner.iter_mut().zip(g.iter()) {
+                *gr = g.inner[oy];
+            }
+
+            let dp = self.kernel.back_prop_batch(&mut self.acc, p, &gr);
+
+            for ox in 0..OW {
+                for c in 0..CI {
+                    for ky in 0..K {
+                        for kx in 0..K {
+                            if let Some(xy) = source::<STRIDE, PAD, W>(ox, kx)
+                                .zip(source::<STRIDE, PAD, H>(oy, ky))
+                            {
+                                d[c][xy] += dp[(ox, c * K * K + ky * K + kx)];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.kernel.apply_in_place(&mut self.acc, sf, &mut self.opt);
+        self.acc.reset();
+    }
+}
+
+// `N` must be `CI * K * K` and `OW`/`OH` must be `(W - 1) STRIDE - 2 PAD + K`, both are checked
+// at compile time.
+//
+// the kernel has the same layout as `Conv2d`'s, every input pixel scatters its channels through
+// it onto a patch of the output
+#[derive(Debug)]
+pub struct ConvTranspose2d<
+    const CI: usize,
+    const CO: usize,
+    const K: usize,
+    const STRIDE: usize,
+    const PAD: usize,
+    const W: usize,
+    const H: usize,
+    const N: usize,
+    const OW: usize,
+    const OH: usize,
+    Opt,
+> {
+    pub kernel: Layer<N, CO>,
+    pub opt: Opt,
+    acc: BackPropAcc<N, CO>,
+    input: [Matrix<W, H>; CI],
+}
+
+impl<
+        const CI: usize,
+        const CO: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const W: usize,
+        const H: usize,
+        const N: usize,
+        const OW: usize,
+        const OH: usize,
+        Opt: Optimizer<N, CO>,
+    > ConvTranspose2d<CI, CO, K, STRIDE, PAD, W, H, N, OW, OH, Opt>
+{
+    pub const fn new(kernel: Layer<N, CO>, opt: Opt) -> Self {
+        Self {
+            kernel,
+            opt,
+            acc: BackPropAcc::new(),
+            input: [const { Matrix::new_zeroed() }; CI],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized(opt: Opt) -> Self {
+        Self::new(Layer::new_randomized(), opt)
+    }
+}
+
+impl<
+        const CI: usize,
+        const CO: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const W: usize,
+        const H: usize,
+        const N: usize,
+        const OW: usize,
+        const OH: usize,
+        Opt: Optimizer<N, CO>,
+    > Module<[Matrix<W, H>; CI]> for ConvTranspose2d<CI, CO, K, STRIDE, PAD, W, H, N, OW, OH, Opt>
+{
+    type Output = [Matrix<OW, OH>; CO];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; CI]) -> [Matrix<OW, OH>; CO] {
+        const {
+            assert!(N == CI * K * K);
+            assert!(OW + 2 * PAD == (W - 1) * STRIDE + K);
+            assert!(OH + 2 * PAD == (H - 1) * STRIDE + K);
+        }
+
+        let mut o: [Matrix<OW, OH>; CO] = core::array::from_fn(|co| Matrix::new_zeroed() + self.kernel.biases[co]);
+
+        // output coordinates map back to input ones the same way as in `Conv2d`, just reversed
+        for y in 0..H {
+            for x in 0..W {
+                for ky in 0..K {
+                    for kx in 0..K {
+                        let Some(xy) = source::<STRIDE, PAD, OW>(x, kx).zip(source::<STRIDE, PAD, OH>(y, ky)) else {
+                            continue;
+                        };
+
+                        for (co, o) in o.iter_mut().enumerate() {
+                            for (c, i) in i.iter().enumerate() {
+                                o[xy] += self.kernel.weights[(c * K * K + ky * K + kx, co)] * i[(x, y)];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.input = i.clone();
+        o
+    }
+
+    fn backward(&mut self, g: &[Matrix<OW, OH>; CO]) -> [Matrix<W, H>; CI] {
+        let mut d = core::array::from_fn(|_| Matrix::<W, H>::new_zeroed());
+
+        for (co, g) in g.iter().enumerate() {
+            self.acc.0.biases[co] += g.inner.iter().flatten().sum::<f32>();
+        }
+
+        for y in 0..H {
+            for x in 0..W {
+                for ky in 0..K {
+                    for kx in 0..K {
+                        let Some(xy) = source::<STRIDE, PAD, OW>(x, kx).zip(source::<STRIDE, PAD, OH>(y, ky)) else {
+                            continue;
+                        };
+
+                        for (co, g) in g.iter().enumerate() {
+                            for (c, d) in d.iter_mut().enumerate() {
+                                let n = c * K * K + ky * K + kx;
+                                d[(x, y)] += self.kernel.weights[(n, co)] * g[xy];
+                                self.acc.0.weights[(n, co)] += self.input[c][(x, y)] * g[xy];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.kernel.apply_in_place(&mut self.acc, sf, &mut self.opt);
+        self.acc.reset();
+    }
+}
+
+// `N` must be `K * K` and `OW`/`OH` must be `(W + 2 PAD - K) / STRIDE + 1`, both are checked at
+// compile time.
+//
+// every channel is convolved with its own kernel, row `c` column `ky K + kx`
+#[derive(Debug)]
+pub struct DepthwiseConv2d<
+    const C: usize,
+    const K: usize,
+    const STRIDE: usize,
+    const PAD: usize,
+    const W: usize,
+    const H: usize,
+    const N: usize,
+    const OW: usize,
+    const OH: usize,
+    Opt,
+> {
+    pub kernel: Layer<N, C>,
+    pub opt: Opt,
+    acc: BackPropAcc<N, C>,
+    input: [Matrix<W, H>; C],
+}
+
+impl<
+        const C: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const W: usize,
+        const H: usize,
+        const N: usize,
+        const OW: usize,
+        const OH: usize,
+        Opt: Optimizer<N, C>,
+    > DepthwiseConv2d<C, K, STRIDE, PAD, W, H, N, OW, OH, Opt>
+{
+    pub const fn new(kernel: Layer<N, C>, opt: Opt) -> Self {
+        Self {
+            kernel,
+            opt,
+            acc: BackPropAcc::new(),
+            input: [const { Matrix::new_zeroed() }; C],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized(opt: Opt) -> Self {
+        Self::new(Layer::new_randomized(), opt)
+    }
+}
+
+impl<
+        const C: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const W: usize,
+        const H: usize,
+        const N: usize,
+        const OW: usize,
+        const OH: usize,
+        Opt: Optimizer<N, C>,
+    > Module<[Matrix<W, H>; C]> for DepthwiseConv2d<C, K, STRIDE, PAD, W, H, N, OW, OH, Opt>
+{
+    type Output = [Matrix<OW, OH>; C];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; C]) -> [Matrix<OW, OH>; C] {
+        const {
+            assert!(N == K * K);
+            assert!(OW == (W + 2 * PAD - K) / STRIDE + 1);
+            assert!(OH == (H + 2 * PAD - K) / STRIDE + 1);
+        }
+
+        self.input = i.clone();
+
+        core::array::from_fn(|c| {
+            let mut o = Matrix::new_zeroed() + self.kernel.biases[c];
+
+            for oy in 0..OH {
+                for ox in 0..OW {
+                    for ky in 0..K {
+                        for kx in 0..K {
+                            if let Some(xy) = source::<STRIDE, PAD, W>(ox, kx).zip(source::<STRIDE, PAD, H>(oy, ky)) {
+                                o[(ox, oy)] += self.kernel.weights[(ky * K + kx, c)] * i[c][xy];
+                            }
+                        }
+                    }
+                }
+            }
+
+            o
+        })
+    }
+
+    fn backward(&mut self, g: &[Matrix<OW, OH>; C]) -> [Matrix<W, H>; C] {
+        core::array::from_fn(|c| {
+            let mut d = Matrix::new_zeroed();
+
+            self.acc.0.biases[c] += g[c].inner.iter().flatten().sum::<f32>();
+
+            for oy in 0..OH {
+                for ox in 0..OW {
+                    for ky in 0..K {
+                        for kx in 0..K {
+                            if let Some(xy) = source::<STRIDE, PAD, W>(ox, kx).zip(source::<STRIDE, PAD, H>(oy, ky)) {
+                                d[xy] += self.kernel.weights[(ky * K + kx, c)] * g[c][(ox, oy)];
+                                self.acc.0.weights[(ky * K + kx, c)] += self.input[c][xy] * g[c][(ox, oy)];
+                            }
+                        }
+                    }
+                }
+            }
+
+            d
+        })
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.kernel.apply_in_place(&mut self.acc, sf, &mut self.opt);
+        self.acc.reset();
+    }
+}
+
+// a 1x1 convolution, mixes channels independently at every pixel
+#[derive(Debug)]
+pub struct PointwiseConv2d<const CI: usize, const CO: usize, const W: usize, const H: usize, Opt> {
+    pub kernel: Layer<CI, CO>,
+    pub opt: Opt,
+    acc: BackPropAcc<CI, CO>,
+    // the pixels of every row, one per column
+    input: [Matrix<W, CI>; H],
+}
+
+impl<const CI: usize, const CO: usize, const W: usize, const H: usize, Opt: Optimizer<CI, CO>>
+    PointwiseConv2d<CI, CO, W, H, Opt>
+{
+    pub const fn new(kernel: Layer<CI, CO>, opt: Opt) -> Self {
+        Self {
+            kernel,
+            opt,
+            acc: BackPropAcc::new(),
+            input: [const { Matrix::new_zeroed() }; H],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized(opt: Opt) -> Self {
+        Self::new(Layer::new_randomized(), opt)
+    }
+}
+
+impl<const CI: usize, const CO: usize, const W: usize, const H: usize, Opt: Optimizer<CI, CO>>
+    Module<[Matrix<W, H>; CI]> for PointwiseConv2d<CI, CO, W, H, Opt>
+{
+    type Output = [Matrix<W, H>; CO];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; CI]) -> [Matrix<W, H>; CO] {
+        let mut o = core::array::from_fn(|_| Matrix::new_zeroed());
+
+        for (y, p) in self.input.iter_mut().enumerate() {
+            for (p, i) in p.inner.iter_mut().zip(i.iter()) {
+                *p = i.inner[y];
+            }
+
+            let r = self.kernel.evaluate_batch(p);
+            for (o, r) in o.iter_mut().zip(r.inner.iter()) {
+                o.inner[y] = *r;
+            }
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &[Matrix<W, H>; CO]) -> [Matrix<W, H>; CI] {
+        let mut d = core::array::from_fn(|_| Matrix::new_zeroed());
+
+        for (y, p) in self.input.iter().enumerate() {
+            let mut gr = Matrix::<W, CO>::new_zeroed();
+            for (gr, g) in gr.inner.iter_mut().zip(g.iter()) {
+                *gr = g.inner[y];
+            }
+
+            let dp = self.kernel.back_prop_batch(&mut self.acc, p, &gr);
+            for (d, dp) in d.iter_mut().zip(dp.inner.iter()) {
+                d.inner[y] = *dp;
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.kernel.apply_in_place(&mut self.acc, sf, &mut self.opt);
+        self.acc.reset();
+    }
+}
+
+// a depthwise convolution followed by a pointwise one, `Chain(depthwise, pointwise)`
+pub type SeparableConv2d<
+    const CI: usize,
+    const CO: usize,
+    const K: usize,
+    const STRIDE: usize,
+    const PAD: usize,
+    const W: usize,
+    const H: usize,
+    const N: usize,
+    const OW: usize,
+    const OH: usize,
+    OptD,
+    OptP,
+> = Chain<DepthwiseConv2d<CI, K, STRIDE, PAD, W, H, N, OW, OH, OptD>, PointwiseConv2d<CI, CO, OW, OH, OptP>>;
+
+// signals are `Matrix<L, C>`, one time step per column
+//
+// `N` must be `CI * K` and `OL` must be `(L + 2 PAD - DIL (K - 1) - 1) / STRIDE + 1`, both are
+// checked at compile time.
+//
+// the kernel is a dense layer over flattened patches, row `co` column `ci K + k`
+#[derive(Debug)]
+pub struct Conv1d<
+    const CI: usize,
+    const CO: usize,
+    const K: usize,
+    const STRIDE: usize,
+    const PAD: usize,
+    const DIL: usize,
+    const L: usize,
+    const N: usize,
+    const OL: usize,
+    Opt,
+> {
+    pub kernel: Layer<N, CO>,
+    pub opt: Opt,
+    acc: BackPropAcc<N, CO>,
+    // one patch per column
+    patches: Matrix<OL, N>,
+}
+
+impl<
+        const CI: usize,
+        const CO: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const DIL: usize,
+        const L: usize,
+        const N: usize,
+        const OL: usize,
+        Opt: Optimizer<N, CO>,
+    > Conv1d<CI, CO, K, STRIDE, PAD, DIL, L, N, OL, Opt>
+{
+    pub const fn new(kernel: Layer<N, CO>, opt: Opt) -> Self {
+        Self {
+            kernel,
+            opt,
+            acc: BackPropAcc::new(),
+            patches: Matrix::new_zeroed(),
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized(opt: Opt) -> Self {
+        Self::new(Layer::new_randomized(), opt)
+    }
+}
+
+impl<
+        const CI: usize,
+        const CO: usize,
+        const K: usize,
+        const STRIDE: usize,
+        const PAD: usize,
+        const DIL: usize,
+        const L: usize,
+        const N: usize,
+        const OL: usize,
+        Opt: Optimizer<N, CO>,
+    > Module<Matrix<L, CI>> for Conv1d<CI, CO, K, STRIDE, PAD, DIL, L, N, OL, Opt>
+{
+    type Output = Matrix<OL, CO>;
+
+    fn forward(&mut self, i: &Matrix<L, CI>) -> Matrix<OL, CO> {
+        const {
+            assert!(N == CI * K);
+            assert!(OL == (L + 2 * PAD - DIL * (K - 1) - 1) / STRIDE + 1);
+        }
+
+        for o in 0..OL {
+            for c in 0..CI {
+                for k in 0..K {
+                    self.patches[(o, c * K + k)] = source::<STRIDE, PAD, L>(o, k * DIL).map_or(0.0, |t| i[(t, c)]);
+                }
+            }
+        }
+
+        self.kernel.evaluate_batch(&self.patches)
+    }
+
+    fn backward(&mut self, g: &Matrix<OL, CO>) -> Matrix<L, CI> {
+        let dp = self.kernel.back_prop_batch(&mut self.acc, &self.patches, g);
+        let mut d = Matrix::new_zeroed();
+
+        for o in 0..OL {
+            for c in 0..CI {
+                for k in 0..K {
+                    if let Some(t) = source::<STRIDE, PAD, L>(o, k * DIL) {
+                        d[(t, c)] += dp[(o, c * K + k)];
+                    }
+                }
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.kernel.apply_in_place(&mut self.acc, sf, &mut self.opt);
+        self.acc.reset();
+    }
+}
+
+// input coordinate read by (dilated) kernel offset `k` at output coordinate `o`, `None` if it lands in the
+// padding
+const fn source<const STRIDE: usize, const PAD: usize, const LEN: usize>(o: usize, k: usize) -> Option<usize> {
+    match (o * STRIDE + k).checked_sub(PAD) {
+        Some(i) if i < LEN => Some(i),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimizers::sgd, testing::*};
+
+    #[test]
+    fn conv2d_gradients() {
+        let new = || Conv2d::<2, 3, 3, 2, 1, 5, 4, 18, 3, 2, _>::new(fill(Layer::new_zeroed(), 3), sgd(1.0));
+
+        assert_input_gradient(new, [const { Matrix::new_zeroed() }; 2]);
+        assert_param_gradient(new, [const { Matrix::new_zeroed() }; 2], |m| &mut m.kernel.weights);
+        assert_param_gradient(new, [const { Matrix::new_zeroed() }; 2], |m| &mut m.kernel.biases);
+    }
+    #[test]
+    fn conv1d_gradients() {
+        let new = || Conv1d::<2, 3, 3, 2, 1, 2, 9, 6, 4, _>::new(fill(Layer::new_zeroed(), 3), sgd(1.0));
+
+        assert_input_gradient(new, Matrix::new_zeroed());
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.kernel);
+    }
+    #[test]
+    fn conv_transpose2d_gradients() {
+        let new = || ConvTranspose2d::<2, 3, 3, 2, 1, 3, 2, 18, 5, 3, _>::new(fill(Layer::new_zeroed(), 3), sgd(1.0));
+
+        assert_input_gradient(new, [const { Matrix::new_zeroed() }; 2]);
+        assert_param_gradient(new, [const { Matrix::new_zeroed() }; 2], |m| &mut m.kernel);
+    }
+    #[test]
+    fn separable_conv2d_gradients() {
+        let new = || -> SeparableConv2d<2, 3, 3, 1, 1, 4, 3, 9, 4, 3, _, _> {
+            Chain(
+                DepthwiseConv2d::new(fill(Layer::new_zeroed(), 3), sgd(1.0)),
+                PointwiseConv2d::new(fill(Layer::new_zeroed(), 4), sgd(1.0)),
+            )
+        };
+
+        assert_input_gradient(new, [const { Matrix::new_zeroed() }; 2]);
+        assert_param_gradient(new, [const { Matrix::new_zeroed() }; 2], |m| &mut m.0.kernel);
+        assert_param_gradient(new, [const { Matrix::new_zeroed() }; 2], |m| &mut m.1.kernel);
+    }
+}
@@ -1,19 +1,83 @@
+use crate::activations::log_softmax;
 use smolmatrix::*;
 
+pub trait Cost<const S: usize> {
+    // returns the loss and its gradient with respect to `x`
+    fn evaluate(&self, x: &Vector<S>, t: &Vector<S>) -> (f32, Vector<S>);
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SquaredError;
+
+impl<const S: usize> Cost<S> for SquaredError {
+    fn evaluate(&self, x: &Vector<S>, t: &Vector<S>) -> (f32, Vector<S>) {
+        let c = squared_error(x.clone(), t).inner.iter().flatten().sum::<f32>();
+        (c, squared_error_derivative(x.clone(), t))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mse;
+
+impl<const S: usize> Cost<S> for Mse {
+    fn evaluate(&self, x: &Vector<S>, t: &Vector<S>) -> (f32, Vector<S>) {
+        let (c, d) = SquaredError.evaluate(x, t);
+        (c / S as f32, d / S as f32)
+    }
+}
+
+// expects `x` to be probabilities, e.g. the output of `activations::Softmax`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrossEntropy;
+
+impl<const S: usize> Cost<S> for CrossEntropy {
+    fn evaluate(&self, x: &Vector<S>, t: &Vector<S>) -> (f32, Vector<S>) {
+        let mut c = 0.0;
+        let mut d = Vector::new_zeroed();
+
+        for i in 0..S {
+            let x = x[i] + 1e-15;
+            c -= t[i] * x.ln();
+            d[i] = -t[i] / x;
+        }
+
+        (c, d)
+    }
+}
+
+// takes raw logits, the softmax is fused into the cost so the gradient is simply `softmax(x) - t`,
+// `t` has to sum to 1 (e.g. be one-hot)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SoftmaxCrossEntropy;
+
+impl<const S: usize> Cost<S> for SoftmaxCrossEntropy {
+    fn evaluate(&self, x: &Vector<S>, t: &Vector<S>) -> (f32, Vector<S>) {
+        let l = log_softmax(x.clone());
+        let c = -l.dot(t);
+
+        (c, l.map_each(|l| *l = l.exp()) - t)
+    }
+}
+
+// `SoftmaxCrossEntropy` with the target given as a class index instead of a one-hot vector
+pub fn softmax_cross_entropy<const S: usize>(x: &Vector<S>, class: usize) -> (f32, Vector<S>) {
+    let l = log_softmax(x.clone());
+    let c = -l[class];
+
+    let mut d = l.map_each(|l| *l = l.exp());
+    d[class] -= 1.0;
+
+    (c, d)
+}
+
+// the sum of the squared errors despite the name, use `Mse` for the mean
 pub fn mse<const S: usize>(x: Vector<S>, e: &Vector<S>) -> f32 {
-    squared_error(x, e)
-        .inner
-        .into_iter()
-        .map(|a| a[0])
-        .sum::<f32>()
+    SquaredError.evaluate(&x, e).0
 }
 
+#[deprecated(note = "this sums the gradient into a scalar, use `Mse` or `SquaredError` for the per-element gradient")]
 pub fn mse_derivative<const S: usize>(x: Vector<S>, e: &Vector<S>) -> f32 {
-    squared_error_derivative(x, e)
-        .inner
-        .into_iter()
-        .map(|a| a[0])
-        .sum::<f32>()
+    squared_error_derivative(x, e).inner.iter().flatten().sum::<f32>()
 }
 
 pub fn squared_error<const S: usize>(x: Vector<S>, e: &Vector<S>) -> Vector<S> {
@@ -23,3 +87,42 @@ pub fn squared_error<const S: usize>(x: Vector<S>, e: &Vector<S>) -> Vector<S> {
 pub fn squared_error_derivative<const S: usize>(x: Vector<S>, e: &Vector<S>) -> Vector<S> {
     (x - e) * 2.0
 }
+
+#[deprecated(note = "this is not cross-entropy, use `SoftmaxCrossEntropy` or `softmax_cross_entropy`")]
+pub fn softmax_cost<const S: usize>(v: Vector<S>, t: &Vector<S>) -> Vector<S> {
+    let t = t.clone().map_each(|i| *i = (*i + 1e-15).ln());
+    -(v * &t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    fn assert_cost<C: Cost<5>>(c: C, x: Vector<5>) {
+        let t = fill(Vector::new_zeroed(), 2).map_each(|t| *t = t.abs());
+        assert_gradient(&x, &c.evaluate(&x, &t).1, |x| c.evaluate(x, &t).0);
+    }
+
+    #[test]
+    fn gradients_match_central_differences() {
+        let x = fill(Vector::new_zeroed(), 1);
+
+        assert_cost(SquaredError, x.clone());
+        assert_cost(Mse, x.clone());
+        assert_cost(CrossEntropy, x.map_each(|x| *x = x.abs() + 0.1));
+    }
+
+    #[test]
+    fn softmax_cross_entropy_gradient() {
+        let x = fill(Vector::<5>::new_zeroed(), 1) * 4.0;
+        let t = vector!(5 [0.0, 0.0, 1.0, 0.0, 0.0]);
+
+        let (c, d) = SoftmaxCrossEntropy.evaluate(&x, &t);
+        assert_gradient(&x, &d, |x| SoftmaxCrossEntropy.evaluate(x, &t).0);
+
+        let (ci, di) = softmax_cross_entropy(&x, 2);
+        assert!((c - ci).abs() < 1e-6);
+        assert!(d.values().zip(di.values()).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::modules::Module;
+use smolmatrix::*;
+
+// `B` samples are processed at once, one per column
+#[derive(Clone, Debug)]
+pub struct Dropout<const S: usize, const B: usize = 1> {
+    pub p: f32,
+    pub training: bool,
+    mask: Matrix<B, S>,
+}
+
+impl<const S: usize, const B: usize> Dropout<S, B> {
+    pub fn new(p: f32) -> Self {
+        assert!((0.0..1.0).contains(&p), "dropout probability must be in [0, 1)");
+
+        Self {
+            p,
+            training: true,
+            mask: Matrix::new_zeroed(),
+        }
+    }
+}
+
+impl<const S: usize, const B: usize> Module<Matrix<B, S>> for Dropout<S, B> {
+    type Output = Matrix<B, S>;
+
+    fn forward(&mut self, i: &Matrix<B, S>) -> Matrix<B, S> {
+        if self.training {
+            let scale = 1.0 / (1.0 - self.p);
+            self.mask.map_each_in_place(|m| *m = if alea::f32() < self.p { 0.0 } else { scale });
+        } else {
+            self.mask.map_each_in_place(|m| *m = 1.0);
+        }
+
+        i.clone().map_zip_ref(&self.mask, |(i, m)| *i *= m)
+    }
+
+    fn backward(&mut self, g: &Matrix<B, S>) -> Matrix<B, S> {
+        g.clone().map_zip_ref(&self.mask, |(g, m)| *g *= m)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn backward_uses_the_forward_mask() {
+        let mut d = Dropout::<8, 4>::new(0.5);
+        let g = fill(Matrix::new_zeroed(), 1);
+
+        // with ones as input the output is the mask itself
+        let mask = d.forward(&Matrix { inner: [[1.0; 4]; 8] });
+        assert!(mask.values().all(|m| *m == 0.0 || *m == 2.0));
+        assert_eq!(d.backward(&g).inner, g.clone().map_zip_ref(&mask, |(g, m)| *g *= m).inner);
+
+        d.set_training(false);
+        assert_eq!(d.forward(&g).inner, g.inner);
+        assert_eq!(d.backward(&g).inner, g.inner);
+    }
+}
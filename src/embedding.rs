@@ -0,0 +1,138 @@
+use crate::optimizers::Optimizer;
+use smolmatrix::*;
+
+// one row per token, updated as the weights of an `Optimizer<DIM, VOCAB>` through
+// `update_weight_rows`, so only the rows of tokens that were seen change
+#[derive(Clone, Debug)]
+pub struct Embedding<const VOCAB: usize, const DIM: usize> {
+    pub table: Matrix<DIM, VOCAB>,
+}
+
+impl<const VOCAB: usize, const DIM: usize> Embedding<VOCAB, DIM> {
+    pub const fn new_zeroed() -> Self {
+        Self {
+            table: Matrix::new_zeroed(),
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized() -> Self {
+        Self::new_each_as(|| alea::f32() - 0.5)
+    }
+
+    pub fn new_each_as<F: Fn() -> f32>(f: F) -> Self {
+        let mut s = Self::new_zeroed();
+        s.table.map_each_in_place(|i| *i = f());
+        s
+    }
+
+    pub fn evaluate(&self, t: usize) -> Vector<DIM> {
+        Vector {
+            inner: self.table.inner[t].map(|i| [i]),
+        }
+    }
+
+    pub fn back_prop(&self, back_prop: &mut EmbeddingAcc<VOCAB, DIM>, t: usize, g: &Vector<DIM>) {
+        for (a, g) in back_prop.grads.inner[t].iter_mut().zip(g.inner.iter()) {
+            *a += g[0];
+        }
+
+        back_prop.used[t] = true;
+    }
+
+    pub fn apply<Opt: Optimizer<DIM, VOCAB>>(
+        &mut self,
+        mut bp: EmbeddingAcc<VOCAB, DIM>,
+        sf: f32,
+        opt: &mut Opt,
+    ) {
+        self.apply_in_place(&mut bp, sf, opt);
+    }
+
+    pub fn apply_in_place<Opt: Optimizer<DIM, VOCAB>>(
+        &mut self,
+        bp: &mut EmbeddingAcc<VOCAB, DIM>,
+        sf: f32,
+        opt: &mut Opt,
+    ) {
+        for (g, _) in bp.grads.inner.iter_mut().zip(bp.used.iter()).filter(|(_, u)| **u) {
+            g.iter_mut().for_each(|g| *g *= sf);
+        }
+
+        opt.update_weight_rows(&mut self.table, &mut bp.grads, &bp.used);
+    }
+}
+
+// the gradients are kept as a whole table since that is what optimizers take, but only the rows
+// of tokens that were seen are ever touched
+#[derive(Debug)]
+pub struct EmbeddingAcc<const VOCAB: usize, const DIM: usize> {
+    grads: Matrix<DIM, VOCAB>,
+    used: [bool; VOCAB],
+}
+
+impl<const VOCAB: usize, const DIM: usize> Default for EmbeddingAcc<VOCAB, DIM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const VOCAB: usize, const DIM: usize> EmbeddingAcc<VOCAB, DIM> {
+    pub const fn new() -> Self {
+        Self {
+            grads: Matrix::new_zeroed(),
+            used: [false; VOCAB],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for (row, used) in self.grads.inner.iter_mut().zip(self.used.iter_mut()).filter(|(_, u)| **u) {
+            *row = [0.0; DIM];
+            *used = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimizers::{adam, sgd}, testing::*};
+
+    #[test]
+    fn gradients_match_central_differences() {
+        let ts = [1, 3, 1];
+        let gs: [Vector<3>; 3] = fill(core::array::from_fn(|_| Vector::new_zeroed()), 1);
+        let cost = |e: &Embedding<5, 3>| ts.iter().zip(gs.iter()).map(|(t, g)| dot(g, &e.evaluate(*t))).sum();
+
+        let mut e = Embedding::<5, 3> {
+            table: fill(Matrix::new_zeroed(), 2),
+        };
+        let table = e.table.clone();
+
+        let mut acc = EmbeddingAcc::new();
+        for (t, g) in ts.iter().zip(gs.iter()) {
+            e.back_prop(&mut acc, *t, g);
+        }
+        e.apply(acc, 1.0, &mut sgd(1.0));
+
+        let d = table.clone() - &e.table;
+        assert_gradient(&table, &d, |table| cost(&Embedding { table: table.clone() }));
+    }
+    #[test]
+    fn only_seen_rows_change() {
+        let mut e = Embedding::<5, 3>::new_zeroed();
+        let mut opt = adam(0.1, 0.9, 0.999);
+
+        for t in [1, 3] {
+            let mut acc = EmbeddingAcc::new();
+            e.back_prop(&mut acc, t, &Vector { inner: [[1.0]; 3] });
+
+            let table = e.table.clone();
+            e.apply(acc, 1.0, &mut opt);
+
+            for (r, (new, old)) in e.table.inner.iter().zip(table.inner.iter()).enumerate() {
+                assert_eq!(new == old, r != t, "row {r}");
+            }
+        }
+    }
+}
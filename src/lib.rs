@@ -24,8 +24,21 @@ use optimizers::Optimizer;
 use smolmatrix::*;
 
 pub mod activations;
+pub mod attention;
+#[cfg(feature = "autograd")]
+pub mod autograd;
+pub mod conv;
 pub mod costs;
+#[cfg(feature = "alea")]
+pub mod dropout;
+pub mod embedding;
+pub mod modules;
+pub mod norm;
 pub mod optimizers;
+pub mod pooling;
+pub mod recurrent;
+#[cfg(test)]
+mod testing;
 
 #[derive(Clone, Debug)]
 pub struct Layer<const IN: usize, const OUT: usize> {
@@ -89,6 +102,35 @@ impl<const IN: usize, const OUT: usize> Layer<IN, OUT> {
         (&(act_der * cost_der).transpose() * &self.weights).transpose() * prev_act_der * i
     }
 
+    // one sample per column
+    pub fn evaluate_batch<const B: usize>(&self, i: &Matrix<B, IN>) -> Matrix<B, OUT> {
+        let mut o = &self.weights * i;
+
+        for (o, [b]) in o.inner.iter_mut().zip(self.biases.inner.iter()) {
+            o.iter_mut().for_each(|o| *o += b);
+        }
+
+        o
+    }
+
+    // `g` is the gradient of the cost w.r.t. the outputs of `evaluate_batch`, returns the
+    // gradient w.r.t. `i`
+    pub fn back_prop_batch<const B: usize>(
+        &self,
+        back_prop: &mut BackPropAcc<IN, OUT>,
+        i: &Matrix<B, IN>,
+        g: &Matrix<B, OUT>,
+    ) -> Matrix<B, IN> {
+        back_prop.0.weights += &(g * &i.transpose());
+
+        for ([b], g) in back_prop.0.biases.inner.iter_mut().zip(g.inner.iter()) {
+            *b += g.iter().sum::<f32>();
+        }
+
+        // gᵀW instead of Wᵀg, which would need a transposed copy of the weights on the stack
+        (&g.transpose() * &self.weights).transpose()
+    }
+
     pub fn apply<Opt: Optimizer<IN, OUT>>(
         &mut self,
         bp: BackPropAcc<IN, OUT>,
@@ -124,4 +166,9 @@ impl<const I: usize, const O: usize> BackPropAcc<I, O> {
     pub const fn new() -> Self {
         Self(Layer::new_zeroed())
     }
+
+    pub fn reset(&mut self) {
+        self.0.weights.map_each_in_place(|i| *i = 0.0);
+        self.0.biases.map_each_in_place(|i| *i = 0.0);
+    }
 }
@@ -0,0 +1,376 @@
+use crate::{activations::Activation, optimizers::Optimizer, BackPropAcc, Layer};
+use core::ops::Add;
+use smolmatrix::*;
+
+pub trait Module<I> {
+    type Output;
+
+    // remembers whatever `backward` needs from this input
+    fn forward(&mut self, i: &I) -> Self::Output;
+
+    // takes the gradient of the cost w.r.t. the output of the last `forward`, accumulates the
+    // parameter gradients and returns the gradient w.r.t. its input
+    fn backward(&mut self, g: &Self::Output) -> I;
+
+    // applies the accumulated gradients scaled by `sf` and clears them
+    fn update(&mut self, _sf: f32) {}
+
+    // switches between training and inference behaviour, e.g. for dropout
+    fn set_training(&mut self, _training: bool) {}
+}
+
+#[cfg(feature = "alloc")]
+impl<I, M: Module<I> + ?Sized> Module<I> for alloc::boxed::Box<M> {
+    type Output = M::Output;
+
+    fn forward(&mut self, i: &I) -> Self::Output {
+        M::forward(self, i)
+    }
+
+    fn backward(&mut self, g: &Self::Output) -> I {
+        M::backward(self, g)
+    }
+
+    fn update(&mut self, sf: f32) {
+        M::update(self, sf)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        M::set_training(self, training)
+    }
+}
+
+// `B` samples are processed at once, one per column
+#[derive(Debug)]
+pub struct Dense<const IN: usize, const OUT: usize, Opt, const B: usize = 1> {
+    pub layer: Layer<IN, OUT>,
+    pub opt: Opt,
+    acc: BackPropAcc<IN, OUT>,
+    input: Matrix<B, IN>,
+}
+
+impl<const IN: usize, const OUT: usize, Opt: Optimizer<IN, OUT>, const B: usize> Dense<IN, OUT, Opt, B> {
+    pub const fn new(layer: Layer<IN, OUT>, opt: Opt) -> Self {
+        Self {
+            layer,
+            opt,
+            acc: BackPropAcc::new(),
+            input: Matrix::new_zeroed(),
+        }
+    }
+}
+
+impl<const IN: usize, const OUT: usize, Opt: Optimizer<IN, OUT>, const B: usize> Module<Matrix<B, IN>>
+    for Dense<IN, OUT, Opt, B>
+{
+    type Output = Matrix<B, OUT>;
+
+    fn forward(&mut self, i: &Matrix<B, IN>) -> Matrix<B, OUT> {
+        self.input = i.clone();
+        self.layer.evaluate_batch(i)
+    }
+
+    fn backward(&mut self, g: &Matrix<B, OUT>) -> Matrix<B, IN> {
+        self.layer.back_prop_batch(&mut self.acc, &self.input, g)
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.layer.apply_in_place(&mut self.acc, sf, &mut self.opt);
+        self.acc.reset();
+    }
+}
+
+// a leaky relu with one learnable slope per feature, `B` samples are processed at once, one per
+// column
+//
+// `slope` is updated as the weights of an `Optimizer<1, S>`
+#[derive(Clone, Debug)]
+pub struct PRelu<const S: usize, Opt, const B: usize = 1> {
+    pub slope: Vector<S>,
+    pub opt: Opt,
+    d_slope: Vector<S>,
+    input: Matrix<B, S>,
+}
+
+impl<const S: usize, Opt: Optimizer<1, S>, const B: usize> PRelu<S, Opt, B> {
+    pub const fn new(opt: Opt) -> Self {
+        Self {
+            slope: Vector { inner: [[0.25]; S] },
+            opt,
+            d_slope: Vector::new_zeroed(),
+            input: Matrix::new_zeroed(),
+        }
+    }
+}
+
+impl<const S: usize, Opt: Optimizer<1, S>, const B: usize> Module<Matrix<B, S>> for PRelu<S, Opt, B> {
+    type Output = Matrix<B, S>;
+
+    fn forward(&mut self, i: &Matrix<B, S>) -> Matrix<B, S> {
+        self.input = i.clone();
+
+        let mut o = i.clone();
+        for (o, a) in o.inner.iter_mut().zip(self.slope.inner.iter()) {
+            o.iter_mut().filter(|o| **o <= 0.0).for_each(|o| *o *= a[0]);
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &Matrix<B, S>) -> Matrix<B, S> {
+        let mut d = g.clone();
+
+        for s in 0..S {
+            for (x, d) in self.input.inner[s].iter().zip(d.inner[s].iter_mut()) {
+                if *x <= 0.0 {
+                    self.d_slope[s] += *x * *d;
+                    *d *= self.slope[s];
+                }
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.d_slope *= sf;
+        self.opt.update_weights(&mut self.slope, &mut self.d_slope);
+        self.d_slope.map_each_in_place(|i| *i = 0.0);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Activate<A, T> {
+    pub f: A,
+    z: Option<T>,
+}
+
+impl<A, T> Activate<A, T> {
+    pub const fn new(f: A) -> Self {
+        Self { f, z: None }
+    }
+}
+
+impl<const B: usize, const S: usize, A: Activation<S>> Module<Matrix<B, S>> for Activate<A, Matrix<B, S>> {
+    type Output = Matrix<B, S>;
+
+    fn forward(&mut self, i: &Matrix<B, S>) -> Matrix<B, S> {
+        let mut a = Matrix::new_zeroed();
+
+        for x in 0..B {
+            set_column(&mut a, x, &self.f.forward(column(i, x)));
+        }
+
+        self.z = Some(i.clone());
+        a
+    }
+
+    fn backward(&mut self, g: &Matrix<B, S>) -> Matrix<B, S> {
+        let z = self.z.take().expect("backward called without a forward pass");
+        let mut d = Matrix::new_zeroed();
+
+        for x in 0..B {
+            set_column(&mut d, x, &self.f.backward(column(&z, x), &column(g, x)));
+        }
+
+        d
+    }
+}
+
+impl<const C: usize, const W: usize, const H: usize, A: Activation<H>> Module<[Matrix<W, H>; C]>
+    for Activate<A, [Matrix<W, H>; C]>
+{
+    type Output = [Matrix<W, H>; C];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; C]) -> [Matrix<W, H>; C] {
+        let a = core::array::from_fn(|c| {
+            let mut a = Matrix::new_zeroed();
+
+            for x in 0..W {
+                set_column(&mut a, x, &self.f.forward(column(&i[c], x)));
+            }
+
+            a
+        });
+
+        self.z = Some(i.clone());
+        a
+    }
+
+    fn backward(&mut self, g: &[Matrix<W, H>; C]) -> [Matrix<W, H>; C] {
+        let z = self.z.take().expect("backward called without a forward pass");
+
+        core::array::from_fn(|c| {
+            let mut d = Matrix::new_zeroed();
+
+            for x in 0..W {
+                set_column(&mut d, x, &self.f.backward(column(&z[c], x), &column(&g[c], x)));
+            }
+
+            d
+        })
+    }
+}
+
+// feature maps to a vector, channel by channel and row by row, `N` must be `C * W * H`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Flatten<const C: usize, const W: usize, const H: usize, const N: usize>;
+
+impl<const C: usize, const W: usize, const H: usize, const N: usize> Module<[Matrix<W, H>; C]>
+    for Flatten<C, W, H, N>
+{
+    type Output = Vector<N>;
+
+    fn forward(&mut self, i: &[Matrix<W, H>; C]) -> Vector<N> {
+        const { assert!(N == C * W * H) };
+
+        let mut o = Vector::new_zeroed();
+        for (o, i) in o.inner.iter_mut().zip(i.iter().flat_map(|m| m.inner.iter().flatten())) {
+            o[0] = *i;
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &Vector<N>) -> [Matrix<W, H>; C] {
+        let mut d = core::array::from_fn(|_| Matrix::<W, H>::new_zeroed());
+        for (d, g) in d.iter_mut().flat_map(|m| m.inner.iter_mut().flatten()).zip(g.inner.iter()) {
+            *d = g[0];
+        }
+
+        d
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Chain<A, B>(pub A, pub B);
+
+impl<I, A: Module<I>, B: Module<A::Output>> Module<I> for Chain<A, B> {
+    type Output = B::Output;
+
+    fn forward(&mut self, i: &I) -> B::Output {
+        let m = self.0.forward(i);
+        self.1.forward(&m)
+    }
+
+    fn backward(&mut self, g: &B::Output) -> I {
+        let g = self.1.backward(g);
+        self.0.backward(&g)
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.0.update(sf);
+        self.1.update(sf);
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.0.set_training(training);
+        self.1.set_training(training);
+    }
+}
+
+// LIGHT:
+// y = x + M(x)
+#[derive(Clone, Copy, Debug)]
+pub struct Residual<M>(pub M);
+
+impl<I: for<'a> Add<&'a I, Output = I>, M: Module<I, Output = I>> Module<I> for Residual<M> {
+    type Output = I;
+
+    fn forward(&mut self, i: &I) -> I {
+        self.0.forward(i) + i
+    }
+
+    fn backward(&mut self, g: &I) -> I {
+        self.0.backward(g) + g
+    }
+
+    fn update(&mut self, sf: f32) {
+        self.0.update(sf);
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.0.set_training(training);
+    }
+}
+
+#[macro_export]
+macro_rules! sequential {
+    ($m: expr $(,)?) => {
+        $m
+    };
+    ($m: expr, $($rest: expr),+ $(,)?) => {
+        $crate::modules::Chain($m, $crate::sequential!($($rest),+))
+    };
+}
+
+fn column<const W: usize, const H: usize>(m: &Matrix<W, H>, x: usize) -> Vector<H> {
+    let mut v = Vector::new_zeroed();
+
+    for y in 0..H {
+        v[y] = m[(x, y)];
+    }
+
+    v
+}
+
+fn set_column<const W: usize, const H: usize>(m: &mut Matrix<W, H>, x: usize, v: &Vector<H>) {
+    for y in 0..H {
+        m[(x, y)] = v[y];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimizers::sgd, testing::*};
+
+    fn layer() -> Layer<3, 4> {
+        Layer {
+            weights: fill(Matrix::new_zeroed(), 3),
+            biases: fill(Matrix::new_zeroed(), 4),
+        }
+    }
+
+    #[test]
+    fn dense_gradients() {
+        let new = || Dense::<3, 4, _>::new(layer(), sgd(1.0));
+
+        assert_input_gradient(new, Vector::new_zeroed());
+        assert_param_gradient(new, Vector::new_zeroed(), |m| &mut m.layer.weights);
+        assert_param_gradient(new, Vector::new_zeroed(), |m| &mut m.layer.biases);
+    }
+
+    #[test]
+    fn dense_batch_gradients() {
+        let new = || Dense::<3, 4, _, 5>::new(layer(), sgd(1.0));
+
+        assert_input_gradient(new, Matrix::new_zeroed());
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.layer.weights);
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.layer.biases);
+    }
+
+    #[test]
+    fn prelu_gradients() {
+        let new = || {
+            let mut m = PRelu::<4, _, 3>::new(sgd(1.0));
+            m.slope = fill(Vector::new_zeroed(), 3);
+            m
+        };
+
+        assert_input_gradient(new, Matrix::new_zeroed());
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.slope);
+    }
+
+    #[test]
+    fn flatten_gradient() {
+        assert_input_gradient(|| Flatten::<2, 3, 4, 24>, [const { Matrix::new_zeroed() }; 2]);
+    }
+    #[test]
+    fn residual_gradients() {
+        let new = || Residual(Dense::<4, 4, _, 2>::new(fill(Layer::new_zeroed(), 3), sgd(1.0)));
+
+        assert_input_gradient(new, Matrix::new_zeroed());
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.0.layer);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{modules::Module, optimizers::Optimizer};
+use smolmatrix::*;
+
+// normalizes every feature over the batch, `B` samples are processed at once, one per column
+//
+// training needs more than one sample per batch, a single sample has no variance, so `B = 1` is
+// only usable in inference mode where the running statistics are used
+//
+// `gamma` and `beta` are updated as the weights and biases of an `Optimizer<1, S>`
+#[derive(Clone, Debug)]
+pub struct BatchNorm<const S: usize, Opt, const B: usize = 1> {
+    pub gamma: Vector<S>,
+    pub beta: Vector<S>,
+    pub running_mean: Vector<S>,
+    pub running_var: Vector<S>,
+    pub momentum: f32,
+    pub epsilon: f32,
+    pub training: bool,
+    pub opt: Opt,
+    d_gamma: Vector<S>,
+    d_beta: Vector<S>,
+    x_hat: Matrix<B, S>,
+    inv_std: Vector<S>,
+}
+
+impl<const S: usize, Opt: Optimizer<1, S>, const B: usize> BatchNorm<S, Opt, B> {
+    pub const fn new(opt: Opt) -> Self {
+        Self {
+            gamma: Vector { inner: [[1.0]; S] },
+            beta: Vector::new_zeroed(),
+            running_mean: Vector::new_zeroed(),
+            running_var: Vector { inner: [[1.0]; S] },
+            momentum: 0.1,
+            epsilon: 1e-5,
+            training: true,
+            opt,
+            d_gamma: Vector::new_zeroed(),
+            d_beta: Vector::new_zeroed(),
+            x_hat: Matrix::new_zeroed(),
+            inv_std: Vector::new_zeroed(),
+        }
+    }
+}
+
+impl<const S: usize, Opt: Optimizer<1, S>, const B: usize> Module<Matrix<B, S>> for BatchNorm<S, Opt, B> {
+    type Output = Matrix<B, S>;
+
+    fn forward(&mut self, i: &Matrix<B, S>) -> Matrix<B, S> {
+        assert!(!self.training || B > 1, "BatchNorm needs more than one sample per batch to train");
+
+        let mut o = Matrix::new_zeroed();
+
+        // each row holds one feature of every sample
+        for s in 0..S {
+            let row = &i.inner[s];
+
+            let (mean, var) = if self.training {
+                let mean = row.iter().sum::<f32>() / B as f32;
+                let var = row.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / B as f32;
+                let unbiased = var * B as f32 / (B - 1) as f32;
+
+                self.running_mean[s] += self.momentum * (mean - self.running_mean[s]);
+                self.running_var[s] += self.momentum * (unbiased - self.running_var[s]);
+
+                (mean, var)
+            } else {
+                (self.running_mean[s], self.running_var[s])
+            };
+
+            self.inv_std[s] = 1.0 / (var + self.epsilon).sqrt();
+
+            for ((x, x_hat), o) in row.iter().zip(self.x_hat.inner[s].iter_mut()).zip(o.inner[s].iter_mut()) {
+                *x_hat = (x - mean) * self.inv_std[s];
+                *o = self.gamma[s] * *x_hat + self.beta[s];
+            }
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &Matrix<B, S>) -> Matrix<B, S> {
+        let mut d = Matrix::new_zeroed();
+
+        for s in 0..S {
+            let (g, x_hat) = (&g.inner[s], &self.x_hat.inner[s]);
+
+            let sum_g = g.iter().sum::<f32>();
+            let sum_g_x_hat = g.iter().zip(x_hat.iter()).map(|(g, x)| g * x).sum::<f32>();
+            self.d_gamma[s] += sum_g_x_hat;
+            self.d_beta[s] += sum_g;
+
+            let k = self.gamma[s] * self.inv_std[s];
+            for b in 0..B {
+                d.inner[s][b] = if self.training {
+                    // LIGHT:
+                    // ∂x_b = γ/(σB) (B ∂y_b - Σ∂y - x̂_b Σ∂y x̂)
+                    k / B as f32 * (B as f32 * g[b] - sum_g - x_hat[b] * sum_g_x_hat)
+                } else {
+                    k * g[b]
+                };
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        apply_affine(&mut self.opt, sf, &mut self.gamma, &mut self.d_gamma, &mut self.beta, &mut self.d_beta);
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+// normalizes every sample over its features, `B` samples are processed at once, one per column
+//
+// `gamma` and `beta` are updated as the weights and biases of an `Optimizer<1, S>`
+#[derive(Clone, Debug)]
+pub struct LayerNorm<const S: usize, Opt, const B: usize = 1> {
+    pub gamma: Vector<S>,
+    pub beta: Vector<S>,
+    pub epsilon: f32,
+    pub opt: Opt,
+    d_gamma: Vector<S>,
+    d_beta: Vector<S>,
+    x_hat: Matrix<B, S>,
+    inv_std: [f32; B],
+}
+
+impl<const S: usize, Opt: Optimizer<1, S>, const B: usize> LayerNorm<S, Opt, B> {
+    pub const fn new(opt: Opt) -> Self {
+        Self {
+            gamma: Vector { inner: [[1.0]; S] },
+            beta: Vector::new_zeroed(),
+            epsilon: 1e-5,
+            opt,
+            d_gamma: Vector::new_zeroed(),
+            d_beta: Vector::new_zeroed(),
+            x_hat: Matrix::new_zeroed(),
+            inv_std: [0.0; B],
+        }
+    }
+}
+
+impl<const S: usize, Opt: Optimizer<1, S>, const B: usize> Module<Matrix<B, S>> for LayerNorm<S, Opt, B> {
+    type Output = Matrix<B, S>;
+
+    fn forward(&mut self, i: &Matrix<B, S>) -> Matrix<B, S> {
+        let mut o = Matrix::new_zeroed();
+
+        for (b, inv_std) in self.inv_std.iter_mut().enumerate() {
+            let mean = (0..S).map(|s| i[(b, s)]).sum::<f32>() / S as f32;
+            let var = (0..S).map(|s| (i[(b, s)] - mean).powi(2)).sum::<f32>() / S as f32;
+            *inv_std = 1.0 / (var + self.epsilon).sqrt();
+
+            for s in 0..S {
+                self.x_hat[(b, s)] = (i[(b, s)] - mean) * *inv_std;
+                o[(b, s)] = self.gamma[s] * self.x_hat[(b, s)] + self.beta[s];
+            }
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &Matrix<B, S>) -> Matrix<B, S> {
+        let mut d = Matrix::new_zeroed();
+
+        for (b, inv_std) in self.inv_std.iter().enumerate() {
+            let mut sum_d = 0.0;
+            let mut sum_d_x_hat = 0.0;
+
+            for s in 0..S {
+                let d_x_hat = g[(b, s)] * self.gamma[s];
+                sum_d += d_x_hat;
+                sum_d_x_hat += d_x_hat * self.x_hat[(b, s)];
+
+                self.d_gamma[s] += g[(b, s)] * self.x_hat[(b, s)];
+                self.d_beta[s] += g[(b, s)];
+            }
+
+            for s in 0..S {
+                // LIGHT:
+                // ∂x_s = 1/(σS) (S ∂x̂_s - Σ∂x̂ - x̂_s Σ∂x̂ x̂)
+                let d_x_hat = g[(b, s)] * self.gamma[s];
+                d[(b, s)] = inv_std / S as f32 * (S as f32 * d_x_hat - sum_d - self.x_hat[(b, s)] * sum_d_x_hat);
+            }
+        }
+
+        d
+    }
+
+    fn update(&mut self, sf: f32) {
+        apply_affine(&mut self.opt, sf, &mut self.gamma, &mut self.d_gamma, &mut self.beta, &mut self.d_beta);
+    }
+}
+
+fn apply_affine<const S: usize, Opt: Optimizer<1, S>>(
+    opt: &mut Opt,
+    sf: f32,
+    gamma: &mut Vector<S>,
+    d_gamma: &mut Vector<S>,
+    beta: &mut Vector<S>,
+    d_beta: &mut Vector<S>,
+) {
+    // biases first like `Layer::apply`, optimizers such as `Adam` advance their step counter in
+    // `update_weights`
+    *d_beta *= sf;
+    opt.update_biases(beta, d_beta);
+    *d_gamma *= sf;
+    opt.update_weights(gamma, d_gamma);
+
+    d_gamma.map_each_in_place(|i| *i = 0.0);
+    d_beta.map_each_in_place(|i| *i = 0.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimizers::sgd, testing::*};
+
+    #[test]
+    fn batch_norm_gradients() {
+        let new = |training| {
+            move || {
+                let mut m = BatchNorm::<3, _, 4>::new(sgd(1.0));
+                m.gamma = fill(Vector::new_zeroed(), 3);
+                m.beta = fill(Vector::new_zeroed(), 4);
+                m.running_mean = fill(Vector::new_zeroed(), 5);
+                m.set_training(training);
+                m
+            }
+        };
+
+        for training in [true, false] {
+            assert_input_gradient(new(training), Matrix::new_zeroed());
+            assert_param_gradient(new(training), Matrix::new_zeroed(), |m| &mut m.gamma);
+            assert_param_gradient(new(training), Matrix::new_zeroed(), |m| &mut m.beta);
+        }
+    }
+    #[test]
+    fn batch_norm_single_sample_inference() {
+        let new = || {
+            let mut m = BatchNorm::<3, _>::new(sgd(1.0));
+            m.set_training(false);
+            m
+        };
+
+        assert_input_gradient(new, Vector::new_zeroed());
+    }
+
+    #[test]
+    #[should_panic = "more than one sample"]
+    fn batch_norm_single_sample_training() {
+        BatchNorm::<3, _>::new(sgd(1.0)).forward(&Vector::new_zeroed());
+    }
+
+    #[test]
+    fn layer_norm_gradients() {
+        let new = || {
+            let mut m = LayerNorm::<5, _, 3>::new(sgd(1.0));
+            m.gamma = fill(Vector::new_zeroed(), 3);
+            m.beta = fill(Vector::new_zeroed(), 4);
+            m
+        };
+
+        assert_input_gradient(new, Matrix::new_zeroed());
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.gamma);
+        assert_param_gradient(new, Matrix::new_zeroed(), |m| &mut m.beta);
+    }
+}
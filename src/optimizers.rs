@@ -3,6 +3,17 @@ use smolmatrix::*;
 pub trait Optimizer<const I: usize, const O: usize> {
     fn update_weights(&mut self, p: &mut Matrix<I, O>, g: &mut Matrix<I, O>);
     fn update_biases(&mut self, p: &mut Vector<O>, g: &mut Vector<O>);
+
+    // `update_weights` for sparse gradients, only the rows set in `rows` should change. the default
+    // zeroes the other gradients, which still moves their parameters with optimizers that carry
+    // state between steps
+    fn update_weight_rows(&mut self, p: &mut Matrix<I, O>, g: &mut Matrix<I, O>, rows: &[bool; O]) {
+        for (g, _) in g.inner.iter_mut().zip(rows.iter()).filter(|(_, u)| !**u) {
+            *g = [0.0; I];
+        }
+
+        self.update_weights(p, g);
+    }
 }
 
 #[cfg(feature = "alloc")]
@@ -14,6 +25,10 @@ impl<const I: usize, const O: usize, Opt: Optimizer<I, O>> Optimizer<I, O> for a
     fn update_biases(&mut self, p: &mut Vector<O>, g: &mut Vector<O>) {
         Opt::update_biases(self, p, g)
     }
+
+    fn update_weight_rows(&mut self, p: &mut Matrix<I, O>, g: &mut Matrix<I, O>, rows: &[bool; O]) {
+        Opt::update_weight_rows(self, p, g, rows)
+    }
 }
 
 pub struct Sgd(f32);
@@ -27,6 +42,15 @@ impl<const I: usize, const O: usize> Optimizer<I, O> for Sgd {
         *g *= self.0;
         *p -= &*g;
     }
+
+    fn update_weight_rows(&mut self, p: &mut Matrix<I, O>, g: &mut Matrix<I, O>, rows: &[bool; O]) {
+        for ((p, g), _) in p.inner.iter_mut().zip(g.inner.iter_mut()).zip(rows.iter()).filter(|(_, u)| **u) {
+            for (p, g) in p.iter_mut().zip(g.iter_mut()) {
+                *g *= self.0;
+                *p -= *g;
+            }
+        }
+    }
 }
 
 pub fn sgd(learning_rate: f32) -> Sgd {
@@ -54,6 +78,19 @@ impl<const I: usize, const O: usize> Optimizer<I, O> for SgdMomentum<I, O> {
         self.v_bias += &*g;
         *p -= &self.v_bias;
     }
+
+    // the velocity of the other rows is kept as is instead of decaying
+    fn update_weight_rows(&mut self, p: &mut Matrix<I, O>, g: &mut Matrix<I, O>, rows: &[bool; O]) {
+        let params = p.inner.iter_mut().zip(g.inner.iter_mut());
+
+        for ((p, g), (v, _)) in params.zip(self.v_weight.inner.iter_mut().zip(rows.iter())).filter(|(_, (_, u))| **u) {
+            for ((p, g), v) in p.iter_mut().zip(g.iter_mut()).zip(v.iter_mut()) {
+                *g *= self.alpha;
+                *v = self.beta * *v + *g;
+                *p -= *v;
+            }
+        }
+    }
 }
 
 pub fn sgd_momentum<const I: usize, const O: usize>(
@@ -98,43 +135,42 @@ impl<const I: usize, const O: usize> Optimizer<I, O> for Adam<I, O> {
         // θ^t = θ^(t-1) - -----------
         //                  √^v^t + ε
 
-        self.m_weight *= self.beta1;
-        let g2 = g.clone() * (1.0 - self.beta1);
-        self.m_weight += &g2;
-
-        self.v_weight *= self.beta2;
-        g.map_each_in_place(|i| *i *= *i);
-        *g *= 1.0 - self.beta2;
-        self.v_weight += &*g;
-
-        let m_hat = self.m_weight.clone() / (1.0 - self.beta1.powi(self.t));
-        let mut v_hat = self.v_weight.clone() / (1.0 - self.beta2.powi(self.t));
-
-        // alpha already updated
-        self.t += 1;
-
-        v_hat.map_each_in_place(|i| *i = i.sqrt());
-            *p -= &((m_hat * self.alpha) / &(v_hat + self.epsilon));
+        self.update_weight_rows(p, g, &[true; O]);
     }
 
     fn update_biases(&mut self, p: &mut Vector<O>, g: &mut Vector<O>) {
-        self.m_bias *= self.beta1;
-        let g2 = g.clone() * (1.0 - self.beta1);
-        self.m_bias += &g2;
+        let (c1, c2) = (1.0 - self.beta1.powi(self.t), 1.0 - self.beta2.powi(self.t));
+        let params = p.inner.iter_mut().flatten().zip(g.inner.iter().flatten());
+        let moments = self.m_bias.inner.iter_mut().flatten().zip(self.v_bias.inner.iter_mut().flatten());
 
-        self.v_bias *= self.beta2;
-        g.map_each_in_place(|i| *i *= *i);
-        *g *= 1.0 - self.beta2;
-        self.v_bias += &*g;
-
-        let m_hat = self.m_bias.clone() / (1.0 - self.beta1.powi(self.t));
-        let mut v_hat = self.v_bias.clone() / (1.0 - self.beta2.powi(self.t));
+        for ((p, g), (m, v)) in params.zip(moments) {
+            *m = self.beta1 * *m + (1.0 - self.beta1) * g;
+            *v = self.beta2 * *v + (1.0 - self.beta2) * g * g;
+            *p -= self.alpha * (*m / c1) / ((*v / c2).sqrt() + self.epsilon);
+        }
 
         // self.alpha = (self.alpha * (1.0 - self.beta2.powi(self.t)).sqrt()) / (1.0 - self.beta1.powi(self.t));
         // t should update later
+    }
+
+    // the moments of the other rows are kept as is instead of decaying, and every row is corrected
+    // with the shared step count, so rarely used rows take larger steps than with dense Adam
+    fn update_weight_rows(&mut self, p: &mut Matrix<I, O>, g: &mut Matrix<I, O>, rows: &[bool; O]) {
+        // in place, temporaries the size of the weights don't fit on small (or debug build) stacks
+        let (c1, c2) = (1.0 - self.beta1.powi(self.t), 1.0 - self.beta2.powi(self.t));
+        let params = p.inner.iter_mut().zip(g.inner.iter());
+        let moments = self.m_weight.inner.iter_mut().zip(self.v_weight.inner.iter_mut());
+
+        for ((p, g), ((m, v), _)) in params.zip(moments.zip(rows.iter())).filter(|(_, (_, u))| **u) {
+            for ((p, g), (m, v)) in p.iter_mut().zip(g.iter()).zip(m.iter_mut().zip(v.iter_mut())) {
+                *m = self.beta1 * *m + (1.0 - self.beta1) * g;
+                *v = self.beta2 * *v + (1.0 - self.beta2) * g * g;
+                *p -= self.alpha * (*m / c1) / ((*v / c2).sqrt() + self.epsilon);
+            }
+        }
 
-        v_hat.map_each_in_place(|i| *i = i.sqrt());
-        *p -= &((m_hat * self.alpha) / &(v_hat + self.epsilon));
+        // alpha already updated
+        self.t += 1;
     }
 }
 
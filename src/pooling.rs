@@ -0,0 +1,293 @@
+use crate::modules::Module;
+use smolmatrix::*;
+
+// `OW`/`OH` must be `(W - WIN) / STRIDE + 1`, checked at compile time
+
+#[derive(Clone, Debug)]
+pub struct MaxPool2d<
+    const C: usize,
+    const WIN: usize,
+    const STRIDE: usize,
+    const W: usize,
+    const H: usize,
+    const OW: usize,
+    const OH: usize,
+> {
+    // input coordinates each output was taken from
+    argmax: [[[(usize, usize); OW]; OH]; C],
+}
+
+impl<
+        const C: usize,
+        const WIN: usize,
+        const STRIDE: usize,
+        const W: usize,
+        const H: usize,
+        const OW: usize,
+        const OH: usize,
+    > MaxPool2d<C, WIN, STRIDE, W, H, OW, OH>
+{
+    pub const fn new() -> Self {
+        Self {
+            argmax: [[[(0, 0); OW]; OH]; C],
+        }
+    }
+}
+
+impl<
+        const C: usize,
+        const WIN: usize,
+        const STRIDE: usize,
+        const W: usize,
+        const H: usize,
+        const OW: usize,
+        const OH: usize,
+    > Default for MaxPool2d<C, WIN, STRIDE, W, H, OW, OH>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<
+        const C: usize,
+        const WIN: usize,
+        const STRIDE: usize,
+        const W: usize,
+        const H: usize,
+        const OW: usize,
+        const OH: usize,
+    > Module<[Matrix<W, H>; C]> for MaxPool2d<C, WIN, STRIDE, W, H, OW, OH>
+{
+    type Output = [Matrix<OW, OH>; C];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; C]) -> [Matrix<OW, OH>; C] {
+        const {
+            assert!(OW == (W - WIN) / STRIDE + 1);
+            assert!(OH == (H - WIN) / STRIDE + 1);
+        }
+
+        let mut o = core::array::from_fn(|_| Matrix::<OW, OH>::new_zeroed());
+
+        for (c, (o, am)) in o.iter_mut().zip(self.argmax.iter_mut()).enumerate() {
+            for oy in 0..OH {
+                for ox in 0..OW {
+                    let mut max = (ox * STRIDE, oy * STRIDE);
+
+                    for y in oy * STRIDE..oy * STRIDE + WIN {
+                        for x in ox * STRIDE..ox * STRIDE + WIN {
+                            if i[c][(x, y)] > i[c][max] {
+                                max = (x, y);
+                            }
+                        }
+                    }
+
+                    o[(ox, oy)] = i[c][max];
+                    am[oy][ox] = max;
+                }
+            }
+        }
+
+        o
+    }
+
+    fn backward(&mut self, g: &[Matrix<OW, OH>; C]) -> [Matrix<W, H>; C] {
+        let mut d = core::array::from_fn(|_| Matrix::<W, H>::new_zeroed());
+
+        for ((d, g), am) in d.iter_mut().zip(g.iter()).zip(self.argmax.iter()) {
+            for oy in 0..OH {
+                for ox in 0..OW {
+                    d[am[oy][ox]] += g[(ox, oy)];
+                }
+            }
+        }
+
+        d
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AvgPool2d<
+    const C: usize,
+    const WIN: usize,
+    const STRIDE: usize,
+    const W: usize,
+    const H: usize,
+    const OW: usize,
+    const OH: usize,
+>;
+
+impl<
+        const C: usize,
+        const WIN: usize,
+        const STRIDE: usize,
+        const W: usize,
+        const H: usize,
+        const OW: usize,
+        const OH: usize,
+    > Module<[Matrix<W, H>; C]> for AvgPool2d<C, WIN, STRIDE, W, H, OW, OH>
+{
+    type Output = [Matrix<OW, OH>; C];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; C]) -> [Matrix<OW, OH>; C] {
+        const {
+            assert!(OW == (W - WIN) / STRIDE + 1);
+            assert!(OH == (H - WIN) / STRIDE + 1);
+        }
+
+        let sf = 1.0 / (WIN * WIN) as f32;
+
+        core::array::from_fn(|c| {
+            let mut o = Matrix::new_zeroed();
+
+            for oy in 0..OH {
+                for ox in 0..OW {
+                    for y in oy * STRIDE..oy * STRIDE + WIN {
+                        for x in ox * STRIDE..ox * STRIDE + WIN {
+                            o[(ox, oy)] += i[c][(x, y)] * sf;
+                        }
+                    }
+                }
+            }
+
+            o
+        })
+    }
+
+    fn backward(&mut self, g: &[Matrix<OW, OH>; C]) -> [Matrix<W, H>; C] {
+        let sf = 1.0 / (WIN * WIN) as f32;
+
+        core::array::from_fn(|c| {
+            let mut d = Matrix::new_zeroed();
+
+            for oy in 0..OH {
+                for ox in 0..OW {
+                    for y in oy * STRIDE..oy * STRIDE + WIN {
+                        for x in ox * STRIDE..ox * STRIDE + WIN {
+                            d[(x, y)] += g[c][(ox, oy)] * sf;
+                        }
+                    }
+                }
+            }
+
+            d
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+// `OW`/`OH` must be `W * SCALE`/`H * SCALE`, checked at compile time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Upsample2d<
+    const C: usize,
+    const SCALE: usize,
+    const W: usize,
+    const H: usize,
+    const OW: usize,
+    const OH: usize,
+> {
+    pub mode: Interpolation,
+}
+
+impl<const C: usize, const SCALE: usize, const W: usize, const H: usize, const OW: usize, const OH: usize>
+    Upsample2d<C, SCALE, W, H, OW, OH>
+{
+    pub const fn new(mode: Interpolation) -> Self {
+        Self { mode }
+    }
+
+    // the two input coordinates an output coordinate blends and the weight of the second one
+    fn taps<const LEN: usize>(&self, o: usize) -> (usize, usize, f32) {
+        match self.mode {
+            Interpolation::Nearest => (o / SCALE, o / SCALE, 0.0),
+            Interpolation::Bilinear => {
+                // sample centers are aligned, like `align_corners = false` elsewhere
+                let s = ((o as f32 + 0.5) / SCALE as f32 - 0.5).max(0.0);
+                let i = (s as usize).min(LEN - 1);
+
+                (i, (i + 1).min(LEN - 1), s - i as f32)
+            },
+        }
+    }
+}
+
+impl<const C: usize, const SCALE: usize, const W: usize, const H: usize, const OW: usize, const OH: usize>
+    Module<[Matrix<W, H>; C]> for Upsample2d<C, SCALE, W, H, OW, OH>
+{
+    type Output = [Matrix<OW, OH>; C];
+
+    fn forward(&mut self, i: &[Matrix<W, H>; C]) -> [Matrix<OW, OH>; C] {
+        const {
+            assert!(OW == W * SCALE);
+            assert!(OH == H * SCALE);
+        }
+
+        core::array::from_fn(|c| {
+            let mut o = Matrix::new_zeroed();
+
+            for oy in 0..OH {
+                let (y0, y1, ly) = self.taps::<H>(oy);
+
+                for ox in 0..OW {
+                    let (x0, x1, lx) = self.taps::<W>(ox);
+
+                    o[(ox, oy)] = (1.0 - ly) * ((1.0 - lx) * i[c][(x0, y0)] + lx * i[c][(x1, y0)])
+                        + ly * ((1.0 - lx) * i[c][(x0, y1)] + lx * i[c][(x1, y1)]);
+                }
+            }
+
+            o
+        })
+    }
+
+    fn backward(&mut self, g: &[Matrix<OW, OH>; C]) -> [Matrix<W, H>; C] {
+        core::array::from_fn(|c| {
+            let mut d = Matrix::new_zeroed();
+
+            for oy in 0..OH {
+                let (y0, y1, ly) = self.taps::<H>(oy);
+
+                for ox in 0..OW {
+                    let (x0, x1, lx) = self.taps::<W>(ox);
+                    let g = g[c][(ox, oy)];
+
+                    d[(x0, y0)] += (1.0 - ly) * (1.0 - lx) * g;
+                    d[(x1, y0)] += (1.0 - ly) * lx * g;
+                    d[(x0, y1)] += ly * (1.0 - lx) * g;
+                    d[(x1, y1)] += ly * lx * g;
+                }
+            }
+
+            d
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn max_pool_gradient() {
+        assert_input_gradient(MaxPool2d::<2, 2, 1, 5, 4, 4, 3>::new, [const { Matrix::new_zeroed() }; 2]);
+    }
+
+    #[test]
+    fn avg_pool_gradient() {
+        assert_input_gradient(|| AvgPool2d::<2, 2, 2, 5, 4, 2, 2>, [const { Matrix::new_zeroed() }; 2]);
+    }
+    #[test]
+    fn upsample_gradient() {
+        for mode in [Interpolation::Nearest, Interpolation::Bilinear] {
+            let new = || Upsample2d::<2, 2, 3, 2, 6, 4>::new(mode);
+            assert_input_gradient(new, [const { Matrix::new_zeroed() }; 2]);
+        }
+    }
+}
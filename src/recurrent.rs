@@ -0,0 +1,674 @@
+use crate::{activations::*, optimizers::Optimizer, BackPropAcc, Layer};
+use smolmatrix::*;
+
+// LIGHT:
+// z = W_i x + b_i + W_h h + b_h
+#[derive(Clone, Debug)]
+pub struct Gate<const IN: usize, const HIDDEN: usize> {
+    pub input: Layer<IN, HIDDEN>,
+    pub hidden: Layer<HIDDEN, HIDDEN>,
+}
+
+impl<const IN: usize, const HIDDEN: usize> Gate<IN, HIDDEN> {
+    pub const fn new_zeroed() -> Self {
+        Self {
+            input: Layer::new_zeroed(),
+            hidden: Layer::new_zeroed(),
+        }
+    }
+
+    pub fn new_each_as<F: Fn() -> f32>(f: F) -> Self {
+        Self {
+            input: Layer::new_each_as(&f),
+            hidden: Layer::new_each_as(&f),
+        }
+    }
+
+    pub fn evaluate(&self, x: &Vector<IN>, h: &Vector<HIDDEN>) -> Vector<HIDDEN> {
+        self.input.evaluate(x) + &self.hidden.evaluate(h)
+    }
+
+    // `dz` is the gradient w.r.t. the output of `evaluate`, returns the gradients w.r.t. `x` and
+    // `h`
+    pub fn back_prop(
+        &self,
+        back_prop: &mut GateAcc<IN, HIDDEN>,
+        x: &Vector<IN>,
+        h: &Vector<HIDDEN>,
+        dz: &Vector<HIDDEN>,
+    ) -> (Vector<IN>, Vector<HIDDEN>) {
+        (
+            self.input.back_prop_batch(&mut back_prop.input, x, dz),
+            self.hidden.back_prop_batch(&mut back_prop.hidden, h, dz),
+        )
+    }
+
+    pub fn apply_in_place<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        bp: &mut GateAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut OptI,
+        opt_hidden: &mut OptH,
+    ) {
+        self.input.apply_in_place(&mut bp.input, sf, opt_input);
+        self.hidden.apply_in_place(&mut bp.hidden, sf, opt_hidden);
+    }
+}
+
+#[derive(Debug)]
+pub struct GateAcc<const IN: usize, const HIDDEN: usize> {
+    input: BackPropAcc<IN, HIDDEN>,
+    hidden: BackPropAcc<HIDDEN, HIDDEN>,
+}
+
+impl<const IN: usize, const HIDDEN: usize> Default for GateAcc<IN, HIDDEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const IN: usize, const HIDDEN: usize> GateAcc<IN, HIDDEN> {
+    pub const fn new() -> Self {
+        Self {
+            input: BackPropAcc::new(),
+            hidden: BackPropAcc::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.input.reset();
+        self.hidden.reset();
+    }
+}
+
+// LIGHT:
+// h_t = tanh(W_i x_t + b_i + W_h h_(t-1) + b_h)
+#[derive(Clone, Debug)]
+pub struct Rnn<const IN: usize, const HIDDEN: usize> {
+    pub gate: Gate<IN, HIDDEN>,
+}
+
+impl<const IN: usize, const HIDDEN: usize> Rnn<IN, HIDDEN> {
+    pub const fn new_zeroed() -> Self {
+        Self {
+            gate: Gate::new_zeroed(),
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized() -> Self {
+        Self::new_each_as(|| alea::f32() - 0.5)
+    }
+
+    pub fn new_each_as<F: Fn() -> f32>(f: F) -> Self {
+        Self {
+            gate: Gate::new_each_as(f),
+        }
+    }
+
+    pub fn step(&self, x: &Vector<IN>, h: &Vector<HIDDEN>) -> Vector<HIDDEN> {
+        Tanh.forward(self.gate.evaluate(x, h))
+    }
+
+    // returns the hidden state after every step
+    pub fn evaluate<const T: usize>(&self, h0: &Vector<HIDDEN>, xs: &[Vector<IN>; T]) -> [Vector<HIDDEN>; T] {
+        let mut h = h0.clone();
+
+        xs.each_ref().map(|x| {
+            h = self.step(x, &h);
+            h.clone()
+        })
+    }
+
+    // `hs` is the output of `evaluate` and `gs` the gradient of the cost w.r.t. each of them,
+    // returns the gradient w.r.t. each input
+    //
+    // `None` is full backpropagation through time, `Some(k)` cuts the sequence into chunks of `k`
+    // steps that gradients don't flow between, `k` must be at least 1
+    pub fn back_prop<const T: usize>(
+        &self,
+        back_prop: &mut GateAcc<IN, HIDDEN>,
+        h0: &Vector<HIDDEN>,
+        xs: &[Vector<IN>; T],
+        hs: &[Vector<HIDDEN>; T],
+        gs: &[Vector<HIDDEN>; T],
+        truncate: Option<usize>,
+    ) -> [Vector<IN>; T] {
+        assert!(truncate != Some(0), "truncate must be at least 1");
+
+        let mut dxs = core::array::from_fn(|_| Vector::new_zeroed());
+        let mut dh = Vector::new_zeroed();
+
+        for s in (0..T).rev() {
+            let h_prev = if s == 0 { h0 } else { &hs[s - 1] };
+
+            dh += &gs[s];
+            let dz = Tanh.derivative_from_output(&hs[s]).unwrap() * &dh;
+            let (dx, dh_prev) = self.gate.back_prop(back_prop, &xs[s], h_prev, &dz);
+            dxs[s] = dx;
+
+            dh = if truncate.is_some_and(|k| s % k == 0) { Vector::new_zeroed() } else { dh_prev };
+        }
+
+        dxs
+    }
+
+    pub fn apply<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        mut bp: GateAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut OptI,
+        opt_hidden: &mut OptH,
+    ) {
+        self.apply_in_place(&mut bp, sf, opt_input, opt_hidden);
+    }
+
+    pub fn apply_in_place<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        bp: &mut GateAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut OptI,
+        opt_hidden: &mut OptH,
+    ) {
+        self.gate.apply_in_place(bp, sf, opt_input, opt_hidden);
+    }
+}
+
+// LIGHT:
+// i_t = σ(z_i)      f_t = σ(z_f)
+// g_t = tanh(z_g)   o_t = σ(z_o)
+// c_t = f_t c_(t-1) + i_t g_t
+// h_t = o_t tanh(c_t)
+//
+// `gates` are in the order input, forget, cell, output
+#[derive(Clone, Debug)]
+pub struct Lstm<const IN: usize, const HIDDEN: usize> {
+    pub gates: [Gate<IN, HIDDEN>; 4],
+}
+
+#[derive(Clone, Debug)]
+pub struct LstmState<const HIDDEN: usize> {
+    pub h: Vector<HIDDEN>,
+    pub c: Vector<HIDDEN>,
+    // activated gates, same order as `Lstm::gates`
+    gates: [Vector<HIDDEN>; 4],
+}
+
+impl<const HIDDEN: usize> LstmState<HIDDEN> {
+    pub const fn new(h: Vector<HIDDEN>, c: Vector<HIDDEN>) -> Self {
+        Self {
+            h,
+            c,
+            gates: [const { Vector::new_zeroed() }; 4],
+        }
+    }
+}
+
+impl<const IN: usize, const HIDDEN: usize> Lstm<IN, HIDDEN> {
+    pub const fn new_zeroed() -> Self {
+        Self {
+            gates: [const { Gate::new_zeroed() }; 4],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized() -> Self {
+        Self::new_each_as(|| alea::f32() - 0.5)
+    }
+
+    pub fn new_each_as<F: Fn() -> f32>(f: F) -> Self {
+        Self {
+            gates: core::array::from_fn(|_| Gate::new_each_as(&f)),
+        }
+    }
+
+    pub fn step(&self, x: &Vector<IN>, s: &LstmState<HIDDEN>) -> LstmState<HIDDEN> {
+        let [i, f, g, o] = &self.gates;
+        let i = Sigmoid.forward(i.evaluate(x, &s.h));
+        let f = Sigmoid.forward(f.evaluate(x, &s.h));
+        let g = Tanh.forward(g.evaluate(x, &s.h));
+        let o = Sigmoid.forward(o.evaluate(x, &s.h));
+
+        let c = f.clone() * &s.c + &(i.clone() * &g);
+        let h = Tanh.forward(c.clone()) * &o;
+
+        LstmState { h, c, gates: [i, f, g, o] }
+    }
+
+    // returns the state after every step
+    pub fn evaluate<const T: usize>(&self, s0: &LstmState<HIDDEN>, xs: &[Vector<IN>; T]) -> [LstmState<HIDDEN>; T] {
+        let mut s = s0.clone();
+
+        xs.each_ref().map(|x| {
+            s = self.step(x, &s);
+            s.clone()
+        })
+    }
+
+    // `ss` is the output of `evaluate` and `gs` the gradient of the cost w.r.t. each hidden state,
+    // returns the gradient w.r.t. each input
+    //
+    // `None` is full backpropagation through time, `Some(k)` cuts the sequence into chunks of `k`
+    // steps that gradients don't flow between, `k` must be at least 1
+    pub fn back_prop<const T: usize>(
+        &self,
+        back_prop: &mut LstmAcc<IN, HIDDEN>,
+        s0: &LstmState<HIDDEN>,
+        xs: &[Vector<IN>; T],
+        ss: &[LstmState<HIDDEN>; T],
+        gs: &[Vector<HIDDEN>; T],
+        truncate: Option<usize>,
+    ) -> [Vector<IN>; T] {
+        assert!(truncate != Some(0), "truncate must be at least 1");
+
+        let mut dxs = core::array::from_fn(|_| Vector::new_zeroed());
+        let mut dh = Vector::new_zeroed();
+        let mut dc = Vector::new_zeroed();
+
+        for s in (0..T).rev() {
+            let prev = if s == 0 { s0 } else { &ss[s - 1] };
+            let [i, f, g, o] = &ss[s].gates;
+
+            dh += &gs[s];
+            let tanh_c = Tanh.forward(ss[s].c.clone());
+            dc += &(Tanh.derivative_from_output(&tanh_c).unwrap() * o * &dh);
+
+            let dz = [
+                Sigmoid.derivative_from_output(i).unwrap() * g * &dc,
+                Sigmoid.derivative_from_output(f).unwrap() * &prev.c * &dc,
+                Tanh.derivative_from_output(g).unwrap() * i * &dc,
+                Sigmoid.derivative_from_output(o).unwrap() * &tanh_c * &dh,
+            ];
+
+            dh = Vector::new_zeroed();
+            dc *= f;
+
+            for ((gate, acc), dz) in self.gates.iter().zip(back_prop.gates.iter_mut()).zip(dz.iter()) {
+                let (dx, dh_prev) = gate.back_prop(acc, &xs[s], &prev.h, dz);
+                dxs[s] += &dx;
+                dh += &dh_prev;
+            }
+
+            if truncate.is_some_and(|k| s % k == 0) {
+                dh = Vector::new_zeroed();
+                dc = Vector::new_zeroed();
+            }
+        }
+
+        dxs
+    }
+
+    pub fn apply<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        mut bp: LstmAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut [OptI; 4],
+        opt_hidden: &mut [OptH; 4],
+    ) {
+        self.apply_in_place(&mut bp, sf, opt_input, opt_hidden);
+    }
+
+    pub fn apply_in_place<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        bp: &mut LstmAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut [OptI; 4],
+        opt_hidden: &mut [OptH; 4],
+    ) {
+        for (((gate, acc), oi), oh) in self
+            .gates
+            .iter_mut()
+            .zip(bp.gates.iter_mut())
+            .zip(opt_input.iter_mut())
+            .zip(opt_hidden.iter_mut())
+        {
+            gate.apply_in_place(acc, sf, oi, oh);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LstmAcc<const IN: usize, const HIDDEN: usize> {
+    gates: [GateAcc<IN, HIDDEN>; 4],
+}
+
+impl<const IN: usize, const HIDDEN: usize> Default for LstmAcc<IN, HIDDEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const IN: usize, const HIDDEN: usize> LstmAcc<IN, HIDDEN> {
+    pub const fn new() -> Self {
+        Self {
+            gates: [const { GateAcc::new() }; 4],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.gates.iter_mut().for_each(GateAcc::reset);
+    }
+}
+
+// LIGHT:
+// r_t = σ(z_r)   u_t = σ(z_u)
+// n_t = tanh(W_i x_t + b_i + r_t (W_h h_(t-1) + b_h))
+// h_t = (1 - u_t) n_t + u_t h_(t-1)
+//
+// `gates` are in the order reset, update, new
+#[derive(Clone, Debug)]
+pub struct Gru<const IN: usize, const HIDDEN: usize> {
+    pub gates: [Gate<IN, HIDDEN>; 3],
+}
+
+#[derive(Clone, Debug)]
+pub struct GruState<const HIDDEN: usize> {
+    pub h: Vector<HIDDEN>,
+    // activated gates, same order as `Gru::gates`
+    gates: [Vector<HIDDEN>; 3],
+    // hidden half of the new gate before the reset is applied
+    hn: Vector<HIDDEN>,
+}
+
+impl<const IN: usize, const HIDDEN: usize> Gru<IN, HIDDEN> {
+    pub const fn new_zeroed() -> Self {
+        Self {
+            gates: [const { Gate::new_zeroed() }; 3],
+        }
+    }
+
+    #[cfg(feature = "alea")]
+    pub fn new_randomized() -> Self {
+        Self::new_each_as(|| alea::f32() - 0.5)
+    }
+
+    pub fn new_each_as<F: Fn() -> f32>(f: F) -> Self {
+        Self {
+            gates: core::array::from_fn(|_| Gate::new_each_as(&f)),
+        }
+    }
+
+    pub fn step(&self, x: &Vector<IN>, h: &Vector<HIDDEN>) -> GruState<HIDDEN> {
+        let [r, u, n] = &self.gates;
+        let r = Sigmoid.forward(r.evaluate(x, h));
+        let u = Sigmoid.forward(u.evaluate(x, h));
+        let hn = n.hidden.evaluate(h);
+        let n = Tanh.forward(n.input.evaluate(x) + &(hn.clone() * &r));
+
+        // (1 - u) n + u h = n + u (h - n)
+        let h = n.clone() + &(u.clone() * &(h.clone() - &n));
+
+        GruState { h, gates: [r, u, n], hn }
+    }
+
+    // returns the state after every step
+    pub fn evaluate<const T: usize>(&self, h0: &Vector<HIDDEN>, xs: &[Vector<IN>; T]) -> [GruState<HIDDEN>; T] {
+        let mut h = h0.clone();
+
+        xs.each_ref().map(|x| {
+            let s = self.step(x, &h);
+            h = s.h.clone();
+            s
+        })
+    }
+
+    // `ss` is the output of `evaluate` and `gs` the gradient of the cost w.r.t. each hidden state,
+    // returns the gradient w.r.t. each input
+    //
+    // `None` is full backpropagation through time, `Some(k)` cuts the sequence into chunks of `k`
+    // steps that gradients don't flow between, `k` must be at least 1
+    pub fn back_prop<const T: usize>(
+        &self,
+        back_prop: &mut GruAcc<IN, HIDDEN>,
+        h0: &Vector<HIDDEN>,
+        xs: &[Vector<IN>; T],
+        ss: &[GruState<HIDDEN>; T],
+        gs: &[Vector<HIDDEN>; T],
+        truncate: Option<usize>,
+    ) -> [Vector<IN>; T] {
+        assert!(truncate != Some(0), "truncate must be at least 1");
+
+        let mut dxs = core::array::from_fn(|_| Vector::new_zeroed());
+        let mut dh = Vector::new_zeroed();
+
+        for s in (0..T).rev() {
+            let h_prev = if s == 0 { h0 } else { &ss[s - 1].h };
+            let [r, u, n] = &ss[s].gates;
+            let [gr, gu, gn] = &self.gates;
+            let [ar, au, an] = &mut back_prop.gates;
+
+            dh += &gs[s];
+            let dn = Tanh.derivative_from_output(n).unwrap() * &(dh.clone() - &(dh.clone() * u));
+            let du = Sigmoid.derivative_from_output(u).unwrap() * &(h_prev.clone() - n) * &dh;
+            let dr = Sigmoid.derivative_from_output(r).unwrap() * &ss[s].hn * &dn;
+
+            let mut dh_prev = dh * u;
+
+            dxs[s] = gn.input.back_prop_batch(&mut an.input, &xs[s], &dn);
+            dh_prev += &gn.hidden.back_prop_batch(&mut an.hidden, h_prev, &(dn * r));
+
+            for (gate, acc, dz) in [(gr, ar, dr), (gu, au, du)] {
+                let (dx, dh) = gate.back_prop(acc, &xs[s], h_prev, &dz);
+                dxs[s] += &dx;
+                dh_prev += &dh;
+            }
+
+            dh = if truncate.is_some_and(|k| s % k == 0) { Vector::new_zeroed() } else { dh_prev };
+        }
+
+        dxs
+    }
+
+    pub fn apply<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        mut bp: GruAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut [OptI; 3],
+        opt_hidden: &mut [OptH; 3],
+    ) {
+        self.apply_in_place(&mut bp, sf, opt_input, opt_hidden);
+    }
+
+    pub fn apply_in_place<OptI: Optimizer<IN, HIDDEN>, OptH: Optimizer<HIDDEN, HIDDEN>>(
+        &mut self,
+        bp: &mut GruAcc<IN, HIDDEN>,
+        sf: f32,
+        opt_input: &mut [OptI; 3],
+        opt_hidden: &mut [OptH; 3],
+    ) {
+        for (((gate, acc), oi), oh) in self
+            .gates
+            .iter_mut()
+            .zip(bp.gates.iter_mut())
+            .zip(opt_input.iter_mut())
+            .zip(opt_hidden.iter_mut())
+        {
+            gate.apply_in_place(acc, sf, oi, oh);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GruAcc<const IN: usize, const HIDDEN: usize> {
+    gates: [GateAcc<IN, HIDDEN>; 3],
+}
+
+impl<const IN: usize, const HIDDEN: usize> Default for GruAcc<IN, HIDDEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const IN: usize, const HIDDEN: usize> GruAcc<IN, HIDDEN> {
+    pub const fn new() -> Self {
+        Self {
+            gates: [const { GateAcc::new() }; 3],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.gates.iter_mut().for_each(GateAcc::reset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimizers::sgd, testing::*};
+
+    impl<const IN: usize, const HIDDEN: usize> Values for Gate<IN, HIDDEN> {
+        fn values(&self) -> impl Iterator<Item = &f32> {
+            self.input.values().chain(self.hidden.values())
+        }
+
+        fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+            self.input.values_mut().chain(self.hidden.values_mut())
+        }
+    }
+
+    impl<const IN: usize, const HIDDEN: usize> Values for Rnn<IN, HIDDEN> {
+        fn values(&self) -> impl Iterator<Item = &f32> {
+            self.gate.values()
+        }
+
+        fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+            self.gate.values_mut()
+        }
+    }
+
+    impl<const IN: usize, const HIDDEN: usize> Values for Lstm<IN, HIDDEN> {
+        fn values(&self) -> impl Iterator<Item = &f32> {
+            self.gates.values()
+        }
+
+        fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+            self.gates.values_mut()
+        }
+    }
+
+    impl<const IN: usize, const HIDDEN: usize> Values for Gru<IN, HIDDEN> {
+        fn values(&self) -> impl Iterator<Item = &f32> {
+            self.gates.values()
+        }
+
+        fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+            self.gates.values_mut()
+        }
+    }
+
+    fn sequence<const S: usize>(seed: u32) -> [Vector<S>; 4] {
+        fill(core::array::from_fn(|_| Vector::new_zeroed()), seed)
+    }
+
+    #[test]
+    fn rnn_gradients() {
+        let rnn = Rnn::<3, 4> {
+            gate: fill(Gate::new_zeroed(), 1),
+        };
+        let (h0, xs, gs) = (fill(Vector::new_zeroed(), 2), sequence(3), sequence(4));
+        let cost = |rnn: &Rnn<3, 4>, xs: &[Vector<3>; 4]| dot(&gs, &rnn.evaluate(&h0, xs));
+
+        let mut acc = GateAcc::new();
+        let dxs = rnn.back_prop(&mut acc, &h0, &xs, &rnn.evaluate(&h0, &xs), &gs, None);
+        assert_gradient(&xs, &dxs, |xs| cost(&rnn, xs));
+
+        let mut stepped = rnn.clone();
+        stepped.apply(acc, 1.0, &mut sgd(1.0), &mut sgd(1.0));
+        assert_gradient(&rnn, &step(&rnn, &stepped), |rnn| cost(rnn, &xs));
+    }
+
+    // truncating every 2 steps is the same as two sequences of 2, the second one starting from
+    // where the first stopped
+    #[test]
+    fn rnn_truncation() {
+        let rnn = Rnn::<3, 4> {
+            gate: fill(Gate::new_zeroed(), 1),
+        };
+        let (h0, xs, gs) = (fill(Vector::new_zeroed(), 2), sequence(3), sequence(4));
+        let hs = rnn.evaluate(&h0, &xs);
+
+        let [x0, x1, x2, x3] = xs.clone();
+        let [h_0, h_1, h_2, h_3] = hs.clone();
+        let [g0, g1, g2, g3] = gs.clone();
+        let a = rnn.back_prop(&mut GateAcc::new(), &h0, &[x0, x1], &[h_0, h_1.clone()], &[g0, g1], None);
+        let b = rnn.back_prop(&mut GateAcc::new(), &h_1, &[x2, x3], &[h_2, h_3], &[g2, g3], None);
+
+        let truncated = |t| rnn.back_prop(&mut GateAcc::new(), &h0, &xs, &hs, &gs, t);
+        assert!(truncated(Some(2)).values().eq(a.values().chain(b.values())));
+        assert!(truncated(Some(4)).values().eq(truncated(None).values()));
+    }
+    #[test]
+    fn lstm_gradients() {
+        let lstm = Lstm::<3, 4> {
+            gates: fill(core::array::from_fn(|_| Gate::new_zeroed()), 1),
+        };
+        let s0 = LstmState::new(fill(Vector::new_zeroed(), 2), fill(Vector::new_zeroed(), 5));
+        let (xs, gs) = (sequence(3), sequence(4));
+        let cost = |lstm: &Lstm<3, 4>, xs: &[Vector<3>; 4]| dot(&gs, &lstm.evaluate(&s0, xs).map(|s| s.h));
+
+        let mut acc = LstmAcc::new();
+        let dxs = lstm.back_prop(&mut acc, &s0, &xs, &lstm.evaluate(&s0, &xs), &gs, None);
+        assert_gradient(&xs, &dxs, |xs| cost(&lstm, xs));
+
+        let sgds = || core::array::from_fn(|_| sgd(1.0));
+        let mut stepped = lstm.clone();
+        stepped.apply(acc, 1.0, &mut sgds(), &mut sgds());
+        assert_gradient(&lstm, &step(&lstm, &stepped), |lstm| cost(lstm, &xs));
+    }
+
+    #[test]
+    fn lstm_truncation() {
+        let lstm = Lstm::<3, 4> {
+            gates: fill(core::array::from_fn(|_| Gate::new_zeroed()), 1),
+        };
+        let s0 = LstmState::new(fill(Vector::new_zeroed(), 2), fill(Vector::new_zeroed(), 5));
+        let (xs, gs) = (sequence(3), sequence(4));
+        let ss = lstm.evaluate(&s0, &xs);
+
+        let [x0, x1, x2, x3] = xs.clone();
+        let [s_0, s_1, s_2, s_3] = ss.clone();
+        let [g0, g1, g2, g3] = gs.clone();
+        let a = lstm.back_prop(&mut LstmAcc::new(), &s0, &[x0, x1], &[s_0, s_1.clone()], &[g0, g1], None);
+        let b = lstm.back_prop(&mut LstmAcc::new(), &s_1, &[x2, x3], &[s_2, s_3], &[g2, g3], None);
+
+        let truncated = |t| lstm.back_prop(&mut LstmAcc::new(), &s0, &xs, &ss, &gs, t);
+        assert!(truncated(Some(2)).values().eq(a.values().chain(b.values())));
+        assert!(truncated(Some(4)).values().eq(truncated(None).values()));
+    }
+    #[test]
+    fn gru_gradients() {
+        let gru = Gru::<3, 4> {
+            gates: fill(core::array::from_fn(|_| Gate::new_zeroed()), 1),
+        };
+        let (h0, xs, gs) = (fill(Vector::new_zeroed(), 2), sequence(3), sequence(4));
+        let cost = |gru: &Gru<3, 4>, xs: &[Vector<3>; 4]| dot(&gs, &gru.evaluate(&h0, xs).map(|s| s.h));
+
+        let mut acc = GruAcc::new();
+        let dxs = gru.back_prop(&mut acc, &h0, &xs, &gru.evaluate(&h0, &xs), &gs, None);
+        assert_gradient(&xs, &dxs, |xs| cost(&gru, xs));
+
+        let sgds = || core::array::from_fn(|_| sgd(1.0));
+        let mut stepped = gru.clone();
+        stepped.apply(acc, 1.0, &mut sgds(), &mut sgds());
+        assert_gradient(&gru, &step(&gru, &stepped), |gru| cost(gru, &xs));
+    }
+
+    #[test]
+    fn gru_truncation() {
+        let gru = Gru::<3, 4> {
+            gates: fill(core::array::from_fn(|_| Gate::new_zeroed()), 1),
+        };
+        let (h0, xs, gs) = (fill(Vector::new_zeroed(), 2), sequence(3), sequence(4));
+        let ss = gru.evaluate(&h0, &xs);
+
+        let [x0, x1, x2, x3] = xs.clone();
+        let [s_0, s_1, s_2, s_3] = ss.clone();
+        let [g0, g1, g2, g3] = gs.clone();
+        let a = gru.back_prop(&mut GruAcc::new(), &h0, &[x0, x1], &[s_0, s_1.clone()], &[g0, g1], None);
+        let b = gru.back_prop(&mut GruAcc::new(), &s_1.h, &[x2, x3], &[s_2, s_3], &[g2, g3], None);
+
+        let truncated = |t| gru.back_prop(&mut GruAcc::new(), &h0, &xs, &ss, &gs, t);
+        assert!(truncated(Some(2)).values().eq(a.values().chain(b.values())));
+        assert!(truncated(Some(4)).values().eq(truncated(None).values()));
+    }
+}
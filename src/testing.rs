@@ -0,0 +1,121 @@
+// helpers to check hand-written gradients against central differences
+use crate::{modules::Module, Layer};
+use smolmatrix::*;
+
+pub trait Values {
+    fn values(&self) -> impl Iterator<Item = &f32>;
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut f32>;
+}
+
+impl<const W: usize, const H: usize> Values for Matrix<W, H> {
+    fn values(&self) -> impl Iterator<Item = &f32> {
+        self.inner.iter().flatten()
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.inner.iter_mut().flatten()
+    }
+}
+
+impl<T: Values, const N: usize> Values for [T; N] {
+    fn values(&self) -> impl Iterator<Item = &f32> {
+        self.iter().flat_map(T::values)
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.iter_mut().flat_map(T::values_mut)
+    }
+}
+
+impl<const IN: usize, const OUT: usize> Values for Layer<IN, OUT> {
+    fn values(&self) -> impl Iterator<Item = &f32> {
+        self.weights.values().chain(self.biases.values())
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.weights.values_mut().chain(self.biases.values_mut())
+    }
+}
+
+// deterministic values in [-1, 1), different for every `seed`
+pub fn fill<T: Values>(mut t: T, seed: u32) -> T {
+    let mut x = seed.wrapping_mul(0x9e37_79b9) | 1;
+
+    for v in t.values_mut() {
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        *v = (x >> 8) as f32 / (1 << 23) as f32 - 1.0;
+    }
+
+    t
+}
+
+pub fn dot<T: Values>(a: &T, b: &T) -> f32 {
+    a.values().zip(b.values()).map(|(a, b)| a * b).sum()
+}
+
+// checks `d` against the central differences of `f` around `x`
+pub fn assert_gradient<T: Values + Clone>(x: &T, d: &T, f: impl Fn(&T) -> f32) {
+    const EPSILON: f32 = 1e-3;
+
+    for (i, d) in d.values().enumerate() {
+        let nudged = |e: f32| {
+            let mut x = x.clone();
+            *x.values_mut().nth(i).unwrap() += e;
+            f(&x)
+        };
+
+        let n = (nudged(EPSILON) - nudged(-EPSILON)) / (2.0 * EPSILON);
+        assert!((n - d).abs() <= 1e-2 * (1.0 + n.abs()), "{i}: numerical {n} != analytical {d}");
+    }
+}
+
+// the step from `before` to `after`, which is the gradient an `optimizers::sgd(1.0)` update applied
+pub fn step<T: Values + Clone>(before: &T, after: &T) -> T {
+    let mut d = before.clone();
+    for (d, a) in d.values_mut().zip(after.values()) {
+        *d -= a;
+    }
+
+    d
+}
+
+// `backward` against `forward` on a random input, with `g` as the gradient of a linear cost
+pub fn assert_input_gradient<I, M>(new: impl Fn() -> M, i: I)
+where
+    I: Values + Clone,
+    M: Module<I>,
+    M::Output: Values,
+{
+    let i = fill(i, 1);
+    let mut m = new();
+    let g = fill(m.forward(&i), 2);
+
+    assert_gradient(&i, &m.backward(&g), |i| dot(&g, &new().forward(i)));
+}
+
+// the gradient `backward` accumulates for `param` against `forward`, read back from the step
+// `update(1.0)` takes, so `new` has to use `optimizers::sgd(1.0)`
+pub fn assert_param_gradient<I, M, P>(new: impl Fn() -> M, i: I, param: impl Fn(&mut M) -> &mut P)
+where
+    I: Values + Clone,
+    M: Module<I>,
+    M::Output: Values,
+    P: Values + Clone,
+{
+    let i = fill(i, 1);
+    let mut m = new();
+    let g = fill(m.forward(&i), 2);
+    m.backward(&g);
+
+    let p = param(&mut m).clone();
+    m.update(1.0);
+    let d = step(&p, param(&mut m));
+
+    assert_gradient(&p, &d, |p| {
+        let mut m = new();
+        *param(&mut m) = p.clone();
+        dot(&g, &m.forward(&i))
+    });
+}